@@ -1,5 +1,7 @@
+use calcu_rs::config::CalloutConfig;
 use calcu_rs::parse;
 use chrono::NaiveDate;
+use chrono_tz::Tz;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -14,6 +16,8 @@ pub fn parse_sequence_benchmark(c: &mut Criterion) {
                 black_box(&start_date),
                 black_box(&end_date),
                 black_box(PathBuf::from_str("tests").as_mut().unwrap()),
+                black_box(&CalloutConfig::default()),
+                black_box(&Tz::UTC),
             )
         })
     });
@@ -29,6 +33,8 @@ pub fn parse_sequence_benchmark_100(c: &mut Criterion) {
                 black_box(&start_date),
                 black_box(&end_date),
                 black_box(PathBuf::from_str("tests").as_mut().unwrap()),
+                black_box(&CalloutConfig::default()),
+                black_box(&Tz::UTC),
             )
         })
     });
@@ -44,6 +50,8 @@ pub fn parse_sequence_benchmark_300(c: &mut Criterion) {
                 black_box(&start_date),
                 black_box(&end_date),
                 black_box(PathBuf::from_str("tests").as_mut().unwrap()),
+                black_box(&CalloutConfig::default()),
+                black_box(&Tz::UTC),
             )
         })
     });
@@ -59,6 +67,8 @@ pub fn parse_sequence_benchmark_700(c: &mut Criterion) {
                 black_box(&start_date),
                 black_box(&end_date),
                 black_box(PathBuf::from_str("tests").as_mut().unwrap()),
+                black_box(&CalloutConfig::default()),
+                black_box(&Tz::UTC),
             )
         })
     });
@@ -74,6 +84,8 @@ pub fn parse_sequence_benchmark_long_file(c: &mut Criterion) {
                 black_box(&start_date),
                 black_box(&end_date),
                 black_box(PathBuf::from_str("tests").as_mut().unwrap()),
+                black_box(&CalloutConfig::default()),
+                black_box(&Tz::UTC),
             )
         })
     });
@@ -89,6 +101,8 @@ pub fn parse_sequence_one_year(c: &mut Criterion) {
                 black_box(&start_date),
                 black_box(&end_date),
                 black_box(PathBuf::from_str("tests").as_mut().unwrap()),
+                black_box(&CalloutConfig::default()),
+                black_box(&Tz::UTC),
             )
         })
     });
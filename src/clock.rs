@@ -0,0 +1,160 @@
+use std::{fs, path::Path};
+
+use crate::structs::{WorkSession, TIME_FMT};
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveTime;
+
+/// Returns the session that was clocked in to and not yet clocked out of.
+pub fn running_session(sessions: &[WorkSession]) -> Option<&WorkSession> {
+    sessions.last().filter(|session| session.end.is_none())
+}
+
+/// Appends a `CLOCK IN:` line for the project to the day file, creating the
+/// file if needed.
+pub fn clock_in(
+    path: &Path,
+    sessions: &[WorkSession],
+    project: &str,
+    time: NaiveTime,
+) -> Result<()> {
+    if let Some(session) = running_session(sessions) {
+        bail!(
+            "Already clocked in to {} since {}. Clock out first",
+            session.project.as_deref().unwrap_or("no project"),
+            session.start.format(TIME_FMT.fmt)
+        );
+    }
+
+    let project = project.trim_start_matches('#');
+    append_line(
+        path,
+        &format!("CLOCK IN: {} #{project}", time.format(TIME_FMT.fmt)),
+    )
+}
+
+/// Appends a `CLOCK OUT:` line to the day file.
+pub fn clock_out(
+    path: &Path,
+    sessions: &[WorkSession],
+    time: NaiveTime,
+) -> Result<()> {
+    if running_session(sessions).is_none() {
+        bail!("Not clocked in to anything today");
+    }
+
+    append_line(path, &format!("CLOCK OUT: {}", time.format(TIME_FMT.fmt)))
+}
+
+/// Appends the line as its own paragraph, so that it never continues a list
+/// or a callout above it. Consecutive clock lines are kept together.
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    let mut contents = if path.exists() {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let last_line = contents.lines().last().unwrap_or_default();
+    let separate =
+        !last_line.trim().is_empty() && !last_line.starts_with("CLOCK ");
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    if separate {
+        contents.push('\n');
+    }
+    contents.push_str(line);
+    contents.push('\n');
+
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::CalloutConfig;
+    use crate::parse::parse_sequence;
+    use chrono::{Days, NaiveDate};
+    use chrono_tz::Tz;
+    use tempfile::TempDir;
+
+    #[test]
+    fn check_clock_in_and_out() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 13).unwrap();
+        let dir = TempDir::new().unwrap();
+        let mut notes = dir.path().to_path_buf();
+        let path = notes.join("2025-01-13.md");
+        fs::write(&path, "# Day\n\n> [!NOTE]\n> Started early AT: 08:00 AM")
+            .unwrap();
+
+        let parse = |notes: &mut std::path::PathBuf| {
+            parse_sequence(
+                &date,
+                &date.checked_add_days(Days::new(1)).unwrap(),
+                notes,
+                &CalloutConfig::default(),
+                &Tz::UTC,
+            )
+        };
+
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let sched = parse(&mut notes);
+        assert!(clock_out(&path, &sched.sessions, nine).is_err());
+        clock_in(&path, &sched.sessions, "#calcu-rs", nine).unwrap();
+
+        let sched = parse(&mut notes);
+        assert_eq!(sched.comments[0].comment.trim(), "Started early");
+        assert!(clock_in(&path, &sched.sessions, "other", nine).is_err());
+        let half_past_ten = NaiveTime::from_hms_opt(10, 30, 0).unwrap();
+        clock_out(&path, &sched.sessions, half_past_ten).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Day\n\n> [!NOTE]\n> Started early AT: 08:00 AM\n\n\
+             CLOCK IN: 09:00 AM #calcu-rs\nCLOCK OUT: 10:30 AM\n"
+        );
+        let sched = parse(&mut notes);
+        assert_eq!(sched.comments.len(), 1);
+        assert_eq!(sched.sessions.len(), 1);
+        assert_eq!(sched.sessions[0].project.as_deref(), Some("calcu-rs"));
+        assert_eq!(
+            sched.sessions[0].duration(),
+            Some(chrono::Duration::minutes(90))
+        );
+    }
+
+    #[test]
+    fn check_overnight_session() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 13).unwrap();
+        let dir = TempDir::new().unwrap();
+        let mut notes = dir.path().to_path_buf();
+        fs::write(
+            notes.join("2025-01-13.md"),
+            "CLOCK IN: 11:00 PM #release night #urgent
+",
+        )
+        .unwrap();
+        fs::write(
+            notes.join("2025-01-14.md"),
+            "CLOCK OUT: 01:30 AM
+",
+        )
+        .unwrap();
+
+        let sched = parse_sequence(
+            &date,
+            &date.checked_add_days(Days::new(2)).unwrap(),
+            &mut notes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+        );
+        assert!(sched.diagnostics.is_empty());
+        assert_eq!(sched.sessions.len(), 1);
+        let session = &sched.sessions[0];
+        assert_eq!(session.project.as_deref(), Some("release night"));
+        assert_eq!(session.duration(), Some(chrono::Duration::minutes(150)));
+    }
+}
@@ -2,16 +2,19 @@ use std::{
     default::Default,
     env,
     fs::File,
-    io::{BufReader, Read, Write},
+    io::{self, BufReader, IsTerminal, Read, Write},
     path::PathBuf,
 };
 
 use anyhow::{Context, Result};
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use clap::ValueEnum;
 use derive_builder::Builder;
 use log::{error, trace};
+use pulldown_cmark::BlockQuoteKind;
 use serde::{Deserialize, Serialize};
-use tabled::settings::{Style, Theme};
+use tabled::settings::{Color, Style, Theme};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy)]
 pub enum TableStyle {
@@ -67,18 +70,296 @@ impl From<Alignment> for tabled::settings::Alignment {
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum Colour {
+    Plain,
+    Bold,
+    Dimmed,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl From<Colour> for Color {
+    fn from(value: Colour) -> Self {
+        match value {
+            Colour::Plain => Color::empty(),
+            Colour::Bold => Color::BOLD,
+            Colour::Dimmed => Color::new("\u{1b}[2m", "\u{1b}[22m"),
+            Colour::Black => Color::FG_BLACK,
+            Colour::Red => Color::FG_RED,
+            Colour::Green => Color::FG_GREEN,
+            Colour::Yellow => Color::FG_YELLOW,
+            Colour::Blue => Color::FG_BLUE,
+            Colour::Magenta => Color::FG_MAGENTA,
+            Colour::Cyan => Color::FG_CYAN,
+            Colour::White => Color::FG_WHITE,
+            Colour::BrightBlack => Color::FG_BRIGHT_BLACK,
+            Colour::BrightRed => Color::FG_BRIGHT_RED,
+            Colour::BrightGreen => Color::FG_BRIGHT_GREEN,
+            Colour::BrightYellow => Color::FG_BRIGHT_YELLOW,
+            Colour::BrightBlue => Color::FG_BRIGHT_BLUE,
+            Colour::BrightMagenta => Color::FG_BRIGHT_MAGENTA,
+            Colour::BrightCyan => Color::FG_BRIGHT_CYAN,
+            Colour::BrightWhite => Color::FG_BRIGHT_WHITE,
+        }
+    }
+}
+
+impl From<Colour> for ratatui::style::Style {
+    fn from(value: Colour) -> Self {
+        use ratatui::style::{Color, Modifier, Style};
+
+        match value {
+            Colour::Plain => Style::new(),
+            Colour::Bold => Style::new().add_modifier(Modifier::BOLD),
+            Colour::Dimmed => Style::new().add_modifier(Modifier::DIM),
+            Colour::Black => Style::new().fg(Color::Black),
+            Colour::Red => Style::new().fg(Color::Red),
+            Colour::Green => Style::new().fg(Color::Green),
+            Colour::Yellow => Style::new().fg(Color::Yellow),
+            Colour::Blue => Style::new().fg(Color::Blue),
+            Colour::Magenta => Style::new().fg(Color::Magenta),
+            Colour::Cyan => Style::new().fg(Color::Cyan),
+            Colour::White => Style::new().fg(Color::Gray),
+            Colour::BrightBlack => Style::new().fg(Color::DarkGray),
+            Colour::BrightRed => Style::new().fg(Color::LightRed),
+            Colour::BrightGreen => Style::new().fg(Color::LightGreen),
+            Colour::BrightYellow => Style::new().fg(Color::LightYellow),
+            Colour::BrightBlue => Style::new().fg(Color::LightBlue),
+            Colour::BrightMagenta => Style::new().fg(Color::LightMagenta),
+            Colour::BrightCyan => Style::new().fg(Color::LightCyan),
+            Colour::BrightWhite => Style::new().fg(Color::White),
+        }
+    }
+}
+
+/// Decides whether the tables get coloured.
+#[derive(
+    Deserialize, Serialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq,
+)]
+pub enum ColourChoice {
+    /// Colour only when stdout is a terminal and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColourChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColourChoice::Always => true,
+            ColourChoice::Never => false,
+            ColourChoice::Auto => {
+                let no_color =
+                    env::var("NO_COLOR").is_ok_and(|val| !val.is_empty());
+                if no_color {
+                    trace!("$NO_COLOR was set. Disabling colours");
+                }
+                !no_color && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// What a callout block in a day file gets parsed as.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum EntryType {
+    Log,
+    Schedule,
+    Blocker,
+    Idea,
+    Ignore,
+}
+
+#[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
+pub struct CalloutConfig {
+    #[builder(default = "EntryType::Log")]
+    pub note: EntryType,
+    #[builder(default = "EntryType::Idea")]
+    pub tip: EntryType,
+    #[builder(default = "EntryType::Schedule")]
+    pub important: EntryType,
+    #[builder(default = "EntryType::Blocker")]
+    pub warning: EntryType,
+    #[builder(default = "EntryType::Blocker")]
+    pub caution: EntryType,
+}
+
+impl Default for CalloutConfig {
+    fn default() -> Self {
+        CalloutConfigBuilder::default().build().unwrap()
+    }
+}
+
+impl CalloutConfig {
+    pub fn entry_type(&self, kind: BlockQuoteKind) -> EntryType {
+        match kind {
+            BlockQuoteKind::Note => self.note,
+            BlockQuoteKind::Tip => self.tip,
+            BlockQuoteKind::Important => self.important,
+            BlockQuoteKind::Warning => self.warning,
+            BlockQuoteKind::Caution => self.caution,
+        }
+    }
+}
+
+/// The part of the week `crs free` looks for free time in.
+#[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
+pub struct WorkingHoursConfig {
+    #[builder(default = "NaiveTime::from_hms_opt(9, 0, 0).unwrap()")]
+    pub start: NaiveTime,
+    #[builder(default = "NaiveTime::from_hms_opt(17, 0, 0).unwrap()")]
+    pub end: NaiveTime,
+    #[builder(default = "vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ]")]
+    pub days: Vec<Weekday>,
+    /// How long an event with only a start keeps you busy, like 30m
+    #[builder(default = "String::from(\"30m\")")]
+    pub point_length: String,
+}
+
+impl Default for WorkingHoursConfig {
+    fn default() -> Self {
+        WorkingHoursConfigBuilder::default().build().unwrap()
+    }
+}
+
+/// Where `crs remind` sends its reminders.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum NotifierKind {
+    /// Print them
+    Stdout,
+    /// Run the command of the config with the title and the body
+    Command,
+    /// Show them as desktop notifications over D-Bus
+    DBus,
+}
+
+/// When and how `crs remind` reminds you of events and deadlines.
+#[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
+pub struct ReminderConfig {
+    /// How long before an event or a deadline to remind of it, like 10m or
+    /// 1d. Every one of them fires once
+    #[builder(default = "vec![String::from(\"10m\")]")]
+    pub lead_times: Vec<String>,
+    /// Time deadlines and all day events are due at
+    #[builder(default = "NaiveTime::from_hms_opt(9, 0, 0).unwrap()")]
+    pub due_at: NaiveTime,
+    #[builder(default = "NotifierKind::Stdout")]
+    pub notifier: NotifierKind,
+    /// Command the `Command` notifier runs
+    #[builder(default)]
+    pub command: Option<String>,
+    /// Seconds between two looks at the notes
+    #[builder(default = "60")]
+    pub interval: u64,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        ReminderConfigBuilder::default().build().unwrap()
+    }
+}
+
+/// Files the notes `crs` creates are made from.
+#[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
+pub struct TemplateConfig {
+    /// Template of a new day, relative to the notes folder unless absolute.
+    /// Takes the placeholders `{{date}}`, `{{weekday}}`, `{{carried_todos}}`
+    /// and `{{events_today}}`. Only `crs rollover` fills in carried todos,
+    /// and only events of earlier days that run into the new one can be
+    /// listed. New days only get a heading if unset
+    #[builder(default)]
+    pub day: Option<PathBuf>,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        TemplateConfigBuilder::default().build().unwrap()
+    }
+}
+
 #[derive(Deserialize, Serialize, Builder, Debug)]
 pub struct UpperConfig {
     #[builder(default = "String::from(\"~/notes\")")]
     pub notes_folder: String,
     #[builder(default = "NaiveDate::from_ymd_opt(2001, 01, 14).unwrap()")]
     pub start_date: NaiveDate,
+    /// Zone the notes are written in, unless a day says otherwise. Falls back
+    /// to the zone of the system
+    #[builder(default)]
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    #[builder(default)]
+    #[serde(default)]
+    pub colour: ColourChoice,
+    /// Hide `#tag` and `@context` tokens from the displayed text
+    #[builder(default)]
+    #[serde(default)]
+    pub strip_tags: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub callouts: CalloutConfig,
+    #[builder(default)]
+    #[serde(default)]
+    pub working_hours: WorkingHoursConfig,
+    #[builder(default)]
+    #[serde(default)]
+    pub templates: TemplateConfig,
+    #[builder(default)]
+    #[serde(default)]
+    pub reminders: ReminderConfig,
     pub todos: TodoConfig,
     pub schedule: ScheduleConfig,
     pub comments: CommentConfig,
+    #[builder(default = "default_blockers()")]
+    #[serde(default = "default_blockers")]
+    pub blockers: CommentConfig,
+    #[builder(default = "default_ideas()")]
+    #[serde(default = "default_ideas")]
+    pub ideas: CommentConfig,
+}
+
+fn default_blockers() -> CommentConfig {
+    CommentConfigBuilder::default()
+        .heading("Blockers".to_string())
+        .build()
+        .unwrap()
+}
+
+fn default_ideas() -> CommentConfig {
+    CommentConfigBuilder::default()
+        .heading("Ideas".to_string())
+        .build()
+        .unwrap()
 }
 
 #[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
 pub struct TodoConfig {
     #[builder(default = "TableStyle::Empty")]
     pub table_style: TableStyle,
@@ -86,9 +367,31 @@ pub struct TodoConfig {
     pub margins: (usize, usize, usize, usize),
     #[builder(default = "Alignment::Center")]
     pub alignment: Alignment,
+    #[builder(default = "Colour::Bold")]
+    pub header_colour: Colour,
+    #[builder(default = "Colour::Red")]
+    pub overdue_colour: Colour,
+    #[builder(default = "Colour::Yellow")]
+    pub due_today_colour: Colour,
+    #[builder(default = "Colour::BrightRed")]
+    pub high_priority_colour: Colour,
+    #[builder(default = "Colour::BrightYellow")]
+    pub medium_priority_colour: Colour,
+    #[builder(default = "Colour::Plain")]
+    pub low_priority_colour: Colour,
+    /// Add a column with the id of every todo
+    #[builder(default)]
+    pub show_ids: bool,
+}
+
+impl Default for TodoConfig {
+    fn default() -> Self {
+        TodoConfigBuilder::default().build().unwrap()
+    }
 }
 
 #[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
 pub struct ScheduleConfig {
     #[builder(default = "TableStyle::Empty")]
     pub table_style: TableStyle,
@@ -96,19 +399,51 @@ pub struct ScheduleConfig {
     pub margins: (usize, usize, usize, usize),
     #[builder(default = "Alignment::Center")]
     pub alignment: Alignment,
+    #[builder(default = "Colour::Bold")]
+    pub header_colour: Colour,
+    #[builder(default = "Colour::Dimmed")]
+    pub all_day_colour: Colour,
+    /// Add a column with the id of every event
+    #[builder(default)]
+    pub show_ids: bool,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        ScheduleConfigBuilder::default().build().unwrap()
+    }
 }
 
 #[derive(Deserialize, Serialize, Builder, Debug, Clone)]
+#[serde(default)]
 pub struct CommentConfig {
+    /// Heading of the column holding the entries
+    #[builder(default = "String::from(\"Logs\")")]
+    pub heading: String,
     #[builder(default = "TableStyle::Empty")]
     pub table_style: TableStyle,
     #[builder(default = "(0, 0, 0, 0)")]
     pub margins: (usize, usize, usize, usize),
     #[builder(default = "Alignment::Center")]
     pub alignment: Alignment,
+    #[builder(default = "Colour::Bold")]
+    pub header_colour: Colour,
+    /// Add a column with the id of every callout in the table
+    #[builder(default)]
+    pub show_ids: bool,
+}
+
+impl Default for CommentConfig {
+    fn default() -> Self {
+        CommentConfigBuilder::default().build().unwrap()
+    }
 }
 
 impl UpperConfig {
+    pub fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or_else(system_timezone)
+    }
+
     pub fn try_parse(config_file: &PathBuf) -> Result<Self> {
         let mut buf = String::new();
 
@@ -131,6 +466,19 @@ impl UpperConfig {
     }
 }
 
+pub fn system_timezone() -> Tz {
+    match iana_time_zone::get_timezone() {
+        Ok(name) => name.parse().unwrap_or_else(|e| {
+            error!("Unknown system timezone {name}: {e}. Using UTC");
+            Tz::UTC
+        }),
+        Err(e) => {
+            error!("Failed to get the system timezone: {e}. Using UTC");
+            Tz::UTC
+        }
+    }
+}
+
 pub fn get_config_path() -> Result<PathBuf> {
     let config_dir = if let Ok(config_dir) = env::var("CALCU_RS_HOME") {
         trace!("$CALCU_RS_HOME was defined. Using the value {config_dir}");
@@ -0,0 +1,256 @@
+use std::{env, fs, path::Path, process::Command};
+
+use crate::structs::{AllRegexes, ToDo, DATE_FMT};
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+
+/// Marks the open task on the line of the day file as done.
+pub fn complete_todo(path: &Path, line: usize) -> Result<()> {
+    mark_todo(path, line, 'x')
+}
+
+/// Marks the open task on the line of the day file as migrated to another
+/// day, the way a bullet journal does.
+pub fn migrate_todo(path: &Path, line: usize) -> Result<()> {
+    mark_todo(path, line, '>')
+}
+
+fn mark_todo(path: &Path, line: usize, mark: char) -> Result<()> {
+    let open_task = Regex::new(r"^(\s*[-*+]\s+)\[ \]").unwrap();
+
+    rewrite_line(path, line, |text| {
+        if !open_task.is_match(text) {
+            bail!("Line {line} of {} is not an open task", path.display());
+        }
+        Ok(open_task
+            .replace(text, format!("${{1}}[{mark}]"))
+            .into_owned())
+    })
+}
+
+/// Sets the deadline of the task on the line of the day file. The one it
+/// had is replaced where it was, even on a later line of the task.
+pub fn set_deadline(
+    path: &Path,
+    line: usize,
+    deadline: NaiveDate,
+    all_regexes: &AllRegexes,
+) -> Result<()> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let written = (line..=task_end(&contents, line)).find(|line| {
+        contents
+            .lines()
+            .nth(line - 1)
+            .is_some_and(|text| all_regexes.deadline.is_match(text))
+    });
+
+    let deadline = format!("DEADLINE: {}", deadline.format(DATE_FMT.fmt));
+    match written {
+        Some(written) => rewrite_line(path, written, |text| {
+            Ok(all_regexes.deadline.replace(text, &deadline).into_owned())
+        }),
+        None => rewrite_line(path, line, |text| {
+            Ok(format!("{} {deadline}", text.trim_end()))
+        }),
+    }
+}
+
+/// Last line of the task starting on the line, which goes on until a blank
+/// line or the next list item, quote or heading.
+fn task_end(contents: &str, line: usize) -> usize {
+    let block = Regex::new(r"^\s*([-*+>#]|[0-9]+[.)])").unwrap();
+    let rest = contents
+        .lines()
+        .skip(line)
+        .take_while(|text| !text.trim().is_empty() && !block.is_match(text));
+    line + rest.count()
+}
+
+/// Appends an open task to the day file, creating the file if needed. The
+/// task joins the list at the end of the file, if there is one.
+pub fn add_todo(path: &Path, todo: &str) -> Result<()> {
+    append_tasks(path, &[format!("- [ ] {}", todo.trim())])
+}
+
+/// Appends the lines of tasks to the day file, creating the file if needed.
+/// They join the list at the end of the file, if there is one.
+pub fn append_tasks(path: &Path, tasks: &[String]) -> Result<()> {
+    let mut contents = if path.exists() {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    // Files written on Windows keep their line endings.
+    let ending = if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let task = Regex::new(r"^\s*[-*+]\s+\[[ xX>]\]").unwrap();
+    // Indented lines carry on the task above them.
+    let last_line = contents
+        .lines()
+        .rev()
+        .find(|line| {
+            task.is_match(line) || !line.starts_with(char::is_whitespace)
+        })
+        .unwrap_or_default();
+    let separate = !last_line.trim().is_empty() && !task.is_match(last_line);
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push_str(ending);
+    }
+    if separate {
+        contents.push_str(ending);
+    }
+    for line in tasks {
+        contents.push_str(&line.replace('\n', ending));
+        contents.push_str(ending);
+    }
+
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Writes the todo as an open task, the way `crs fmt` lays it out. A todo
+/// written over several lines keeps them, indented under its text.
+pub fn todo_line(todo: &ToDo) -> String {
+    let indent = "  ".repeat(todo.depth);
+    let text: Vec<&str> = todo.markdown.trim().lines().map(str::trim).collect();
+    let mut line =
+        format!("{indent}- [ ] {}", text.join(&format!("\n{indent}      ")));
+    if let Some(deadline) = todo.deadline {
+        line.push_str(&format!(" DEADLINE: {}", deadline.format(DATE_FMT.fmt)));
+    }
+    if let Some(priority) = todo.priority {
+        line.push_str(&format!(
+            " PRIORITY: {}",
+            priority.to_string().to_lowercase()
+        ));
+    }
+    line
+}
+
+/// Replaces the 1-based line of the file with what `edit` makes of it.
+pub fn rewrite_line(
+    path: &Path,
+    line: usize,
+    edit: impl FnOnce(&str) -> Result<String>,
+) -> Result<()> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let Some(original) = line.checked_sub(1).and_then(|index| lines.get(index))
+    else {
+        bail!("{} has no line {line}", path.display());
+    };
+    let text = original.trim_end_matches(['\n', '\r']);
+    let ending = &original[text.len()..];
+    let edited = edit(text)? + ending;
+    lines[line - 1] = &edited;
+
+    fs::write(path, lines.concat())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Opens the file in `$VISUAL` or `$EDITOR`, falling back to `vi`, and waits
+/// for the editor to exit. Most editors take `+LINE` to jump to a line.
+pub fn open_in_editor(path: &Path, line: Option<usize>) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        bail!("The editor is empty");
+    };
+
+    let mut command = Command::new(program);
+    command.args(words);
+    if let Some(line) = line {
+        command.arg(format!("+{line}"));
+    }
+    let status = command
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run {editor}"))?;
+    if !status.success() {
+        bail!("{editor} exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structs::init_regexes;
+
+    #[test]
+    fn check_line_edits() {
+        let notes = tempfile::tempdir().unwrap();
+        let path = notes.path().join("2025-01-06.md");
+        fs::write(
+            &path,
+            "# Day\r\n- [ ] Ship it DEADLINE: 2025-01-10\r\n  * [x] Done\r\n",
+        )
+        .unwrap();
+
+        let deadline = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        set_deadline(&path, 2, deadline, &init_regexes()).unwrap();
+        complete_todo(&path, 2).unwrap();
+        assert!(complete_todo(&path, 2).is_err());
+        assert!(complete_todo(&path, 3).is_err());
+        assert!(complete_todo(&path, 4).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Day\r\n- [x] Ship it DEADLINE: 2025-02-01\r\n  * [x] Done\r\n"
+        );
+    }
+
+    #[test]
+    fn check_add_todo() {
+        let notes = tempfile::tempdir().unwrap();
+        let path = notes.path().join("2025-01-06.md");
+        fs::write(&path, "# Day\r\n- [ ] Ship it\r\n").unwrap();
+        add_todo(&path, "Done ").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Day\r\n- [ ] Ship it\r\n- [ ] Done\r\n"
+        );
+
+        let path = notes.path().join("2025-01-07.md");
+        add_todo(&path, "First").unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "Some prose")
+            .unwrap();
+        add_todo(&path, "Second").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "- [ ] First\nSome prose\n\n- [ ] Second\n"
+        );
+    }
+
+    #[test]
+    fn check_multiline_deadline() {
+        let notes = tempfile::tempdir().unwrap();
+        let path = notes.path().join("2025-01-06.md");
+        fs::write(
+            &path,
+            "- [ ] Ship it\n  before the freeze DEADLINE: 2025-01-10\n\
+             - [ ] Tag it\n  and push\n",
+        )
+        .unwrap();
+
+        let deadline = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        set_deadline(&path, 1, deadline, &init_regexes()).unwrap();
+        set_deadline(&path, 3, deadline, &init_regexes()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "- [ ] Ship it\n  before the freeze DEADLINE: 2025-02-01\n\
+             - [ ] Tag it DEADLINE: 2025-02-01\n  and push\n"
+        );
+    }
+}
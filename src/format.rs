@@ -0,0 +1,650 @@
+use std::{fs, path::Path};
+
+use crate::structs::{AllRegexes, Diagnostic, DATE_FMT, TIME_FMT};
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveTime};
+use regex::{Captures, Regex};
+
+/// Lenient versions of the keywords, so that `AT: 9:05 pm` or
+/// `DEADLINE: 2024-7-1` get rewritten into their canonical form.
+pub struct FormatRegexes {
+    pub task: Regex,
+    pub quote: Regex,
+    pub at_time: Regex,
+    pub end: Regex,
+    pub for_duration: Regex,
+    pub all_day: Regex,
+    pub deadline: Regex,
+    pub priority: Regex,
+}
+
+pub fn init_format_regexes() -> FormatRegexes {
+    let time = r":\s*([0-9]{1,2}):([0-9]{2})(?:\s*([AaPp])\.?[Mm]\.?)?";
+
+    FormatRegexes {
+        task: Regex::new(r"^(\s*[-*+]\s+\[[ xX]\]\s+)(.*)$").unwrap(),
+        quote: Regex::new(r"^((?:\s*>)+\s?)(.*)$").unwrap(),
+        at_time: Regex::new(&format!(r"\bAT{time}")).unwrap(),
+        end: Regex::new(&format!(r"\bEND{time}")).unwrap(),
+        for_duration: Regex::new(
+            r"\bFOR:\s*((?i:[0-9]+\s*[wdhm](?:\s*[0-9]+\s*[wdhm])*))\b",
+        )
+        .unwrap(),
+        all_day: Regex::new(r"\bALL DAY\b").unwrap(),
+        deadline: Regex::new(
+            r"\bDEADLINE:\s*([0-9]{4})-([0-9]{1,2})-([0-9]{1,2})",
+        )
+        .unwrap(),
+        priority: Regex::new(r"\bPRIORITY:\s*(?i:(high|medium|low))").unwrap(),
+    }
+}
+
+/// A line of a day file that is not in its canonical form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    /// 1-based line number
+    pub line: usize,
+    pub original: String,
+    pub fixed: String,
+}
+
+impl Fix {
+    /// Whether only the case or the spacing of the line is off, which the
+    /// parser copes with anyway.
+    pub fn is_cosmetic(&self) -> bool {
+        let squash = |line: &str| {
+            line.chars()
+                .filter(|chr| !chr.is_whitespace())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        squash(&self.original) == squash(&self.fixed)
+    }
+
+    pub fn to_diagnostic(&self, date: NaiveDate) -> Diagnostic {
+        Diagnostic {
+            date,
+            line: self.line,
+            message: format!(
+                "Callout header `{}` should be `{}`",
+                self.original.trim(),
+                self.fixed
+            ),
+            fixable: true,
+        }
+    }
+}
+
+/// Returns the canonical form of a callout header line, e.g. `> [!NOTE]`.
+pub fn normalise_callout(line: &str, callout: &Regex) -> Option<String> {
+    if !line.trim_start().starts_with('>') {
+        return None;
+    }
+
+    let cap = callout.captures(line)?;
+    let depth = cap[1].matches('>').count();
+    Some(format!(
+        "{} [!{}]",
+        vec![">"; depth].join(" "),
+        cap[2].to_uppercase()
+    ))
+}
+
+/// Tracks the fenced code blocks of a file, which are left as they are.
+#[derive(Default)]
+struct CodeFence {
+    /// Character and length of the fence of the open block
+    open: Option<(char, usize)>,
+}
+
+impl CodeFence {
+    /// Whether the line is part of a fenced code block, fences included.
+    /// Code blocks in callouts are fenced after the quote markers.
+    fn in_code(&mut self, line: &str) -> bool {
+        let text = line
+            .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace());
+        let fence = text.chars().next().filter(|chr| ['`', '~'].contains(chr));
+        let length = fence.map_or(0, |fence| {
+            text.chars().take_while(|chr| *chr == fence).count()
+        });
+
+        match (self.open, fence) {
+            (None, Some(fence)) if length >= 3 => {
+                self.open = Some((fence, length));
+                true
+            }
+            (Some((open, open_length)), Some(fence))
+                if fence == open
+                    && length >= open_length
+                    && text[length..].trim().is_empty() =>
+            {
+                self.open = None;
+                true
+            }
+            (open, _) => open.is_some(),
+        }
+    }
+}
+
+/// Rewrites every callout header of the contents into its canonical form.
+/// Fenced code is left alone.
+pub fn normalise_callouts(
+    contents: &str,
+    callout: &Regex,
+) -> (String, Vec<Fix>) {
+    let mut fixes = Vec::new();
+    let mut normalised = String::with_capacity(contents.len());
+    let mut fence = CodeFence::default();
+
+    for (index, line) in contents.split_inclusive('\n').enumerate() {
+        let (text, ending) = split_line_ending(line);
+        if fence.in_code(text) {
+            normalised.push_str(line);
+            continue;
+        }
+        match normalise_callout(text, callout) {
+            Some(fixed) if fixed != text => {
+                normalised.push_str(&fixed);
+                fixes.push(Fix {
+                    line: index + 1,
+                    original: text.to_string(),
+                    fixed,
+                });
+            }
+            _ => normalised.push_str(text),
+        }
+        normalised.push_str(ending);
+    }
+
+    (normalised, fixes)
+}
+
+/// Rewrites the contents into the canonical layout. Callout headers are
+/// normalised, and the keywords of tasks and callout blocks are moved to the
+/// end of their line in a fixed order, with canonical dates, times and
+/// durations. Callout blocks that follow each other are ordered by their
+/// time, all day ones first. Prose and fenced code are left untouched.
+pub fn format_contents(
+    contents: &str,
+    all_regexes: &AllRegexes,
+    format_regexes: &FormatRegexes,
+) -> (String, Vec<Fix>) {
+    let lines: Vec<(&str, &str)> = contents
+        .split_inclusive('\n')
+        .map(split_line_ending)
+        .collect();
+    let mut formatted: Vec<String> = Vec::with_capacity(lines.len());
+    let mut kinds: Vec<LineKind> = Vec::with_capacity(lines.len());
+    let mut fence = CodeFence::default();
+    let mut in_callout = false;
+
+    for (text, _) in &lines {
+        let (fixed, kind) = if fence.in_code(text) {
+            in_callout = false;
+            (text.to_string(), LineKind::Other)
+        } else if let Some(header) =
+            normalise_callout(text, &all_regexes.callout)
+        {
+            in_callout = true;
+            (header, LineKind::Header)
+        } else if in_callout && format_regexes.quote.is_match(text) {
+            let line =
+                format_keywords(text, &format_regexes.quote, format_regexes);
+            (line, LineKind::Quote)
+        } else {
+            in_callout = false;
+            let kind = if text.trim().is_empty() {
+                LineKind::Blank
+            } else {
+                LineKind::Other
+            };
+            let line =
+                format_keywords(text, &format_regexes.task, format_regexes);
+            (line, kind)
+        };
+        formatted.push(fixed);
+        kinds.push(kind);
+    }
+    order_callouts(&mut formatted, &kinds, format_regexes);
+
+    let mut fixes = Vec::new();
+    let mut output = String::with_capacity(contents.len());
+    for (index, ((text, ending), fixed)) in
+        lines.iter().zip(formatted).enumerate()
+    {
+        if fixed != *text {
+            fixes.push(Fix {
+                line: index + 1,
+                original: text.to_string(),
+                fixed: fixed.clone(),
+            });
+        }
+        output.push_str(&fixed);
+        output.push_str(ending);
+    }
+
+    (output, fixes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineKind {
+    Header,
+    Quote,
+    Blank,
+    Other,
+}
+
+/// Orders every run of callout blocks, separated by nothing but blank lines,
+/// by the first time in each block. The sort is stable and the blank lines
+/// stay where they are, so blocks that are in order are left alone. A run is
+/// only ordered if a blank line follows it and comes before each of its
+/// blocks, as a block without one belongs to the quote or prose around it.
+fn order_callouts(
+    lines: &mut [String],
+    kinds: &[LineKind],
+    format_regexes: &FormatRegexes,
+) {
+    let mut index = 0;
+    while index < lines.len() {
+        if kinds[index] != LineKind::Header {
+            index += 1;
+            continue;
+        }
+
+        // The blocks of the run, and where they and the blank lines between
+        // them go.
+        let mut blocks: Vec<Vec<String>> = Vec::new();
+        let mut layout: Vec<Option<String>> = Vec::new();
+        let start = index;
+        let mut separated = true;
+        while index < lines.len() {
+            match kinds[index] {
+                LineKind::Header => {
+                    separated &=
+                        index == start || kinds[index - 1] == LineKind::Blank;
+                    blocks.push(vec![lines[index].clone()]);
+                    layout.push(None);
+                }
+                LineKind::Quote => blocks
+                    .last_mut()
+                    .expect("Quotes follow a header")
+                    .push(lines[index].clone()),
+                LineKind::Blank
+                    if kinds[index..]
+                        .iter()
+                        .find(|kind| **kind != LineKind::Blank)
+                        == Some(&LineKind::Header) =>
+                {
+                    layout.push(Some(lines[index].clone()));
+                }
+                _ => break,
+            }
+            index += 1;
+        }
+        if !separated
+            || kinds
+                .get(index)
+                .is_some_and(|kind| *kind != LineKind::Blank)
+        {
+            continue;
+        }
+
+        blocks.sort_by_key(|block| block_time(block, format_regexes));
+        let mut blocks = blocks.into_iter();
+        let mut at = start;
+        for slot in layout {
+            let block = match slot {
+                Some(blank) => vec![blank],
+                None => blocks.next().expect("One slot per block"),
+            };
+            for line in block {
+                lines[at] = line;
+                at += 1;
+            }
+        }
+    }
+}
+
+/// The first `AT:` or `END:` time of a callout block.
+fn block_time(
+    block: &[String],
+    format_regexes: &FormatRegexes,
+) -> Option<NaiveTime> {
+    block.iter().find_map(|line| {
+        [&format_regexes.at_time, &format_regexes.end]
+            .into_iter()
+            .filter_map(|search| search.captures(line))
+            .min_by_key(|cap| cap.get(0).expect("Always present").start())
+            .and_then(|cap| canonical_time(&cap))
+    })
+}
+
+/// Moves the keywords of a line matching `line_search` to its end. The first
+/// capture group of `line_search` is the prefix that is kept as is.
+fn format_keywords(
+    line: &str,
+    line_search: &Regex,
+    format_regexes: &FormatRegexes,
+) -> String {
+    let Some(cap) = line_search.captures(line) else {
+        return line.to_string();
+    };
+    let prefix = &cap[1];
+    let mut rest = cap[2].to_string();
+    let mut keywords = Vec::new();
+
+    let mut extract =
+        |search: &Regex, canonical: &dyn Fn(&Captures) -> Option<String>| {
+            if let Some(found) = search.captures(&rest) {
+                if let Some(keyword) = canonical(&found) {
+                    let range = found.get(0).expect("Always present").range();
+                    keywords.push(keyword);
+                    rest.replace_range(range, REMOVED);
+                }
+            }
+        };
+
+    extract(&format_regexes.at_time, &|cap| {
+        canonical_time(cap)
+            .map(|time| format!("AT: {}", time.format(TIME_FMT.fmt)))
+    });
+    extract(&format_regexes.end, &|cap| {
+        canonical_time(cap)
+            .map(|time| format!("END: {}", time.format(TIME_FMT.fmt)))
+    });
+    extract(&format_regexes.for_duration, &|cap| {
+        let duration: String =
+            cap[1].chars().filter(|chr| !chr.is_whitespace()).collect();
+        Some(format!("FOR: {}", duration.to_lowercase()))
+    });
+    extract(&format_regexes.all_day, &|_| Some("ALL DAY".to_string()));
+    extract(&format_regexes.deadline, &|cap| {
+        let date = NaiveDate::from_ymd_opt(
+            cap[1].parse().ok()?,
+            cap[2].parse().ok()?,
+            cap[3].parse().ok()?,
+        )?;
+        Some(format!("DEADLINE: {}", date.format(DATE_FMT.fmt)))
+    });
+    extract(&format_regexes.priority, &|cap| {
+        Some(format!("PRIORITY: {}", cap[1].to_lowercase()))
+    });
+
+    if keywords.is_empty() {
+        return line.to_string();
+    }
+
+    // Only the spacing around the keywords is collapsed, so that the spacing
+    // within inline code stays as it was.
+    let mut words: Vec<&str> = rest
+        .split(REMOVED)
+        .map(str::trim)
+        .filter(|words| !words.is_empty())
+        .collect();
+    words.extend(keywords.iter().map(String::as_str));
+    format!("{prefix}{}", words.join(" "))
+}
+
+/// Marks where a keyword was taken out of a line.
+const REMOVED: &str = "\u{0}";
+
+fn canonical_time(cap: &Captures) -> Option<NaiveTime> {
+    let mut hour: u32 = cap[1].parse().ok()?;
+    let minute: u32 = cap[2].parse().ok()?;
+
+    if let Some(meridiem) = cap.get(3) {
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        hour %= 12;
+        if meridiem.as_str().eq_ignore_ascii_case("p") {
+            hour += 12;
+        }
+    }
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Formats the day file. Unless `check` is set, the file is rewritten in
+/// place. Returns the lines that differ from the canonical layout.
+pub fn format_file(
+    path: &Path,
+    all_regexes: &AllRegexes,
+    format_regexes: &FormatRegexes,
+    check: bool,
+) -> Result<Vec<Fix>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let (formatted, fixes) =
+        format_contents(&contents, all_regexes, format_regexes);
+    if !check && !fixes.is_empty() {
+        fs::write(path, formatted)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(fixes)
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let text = line.trim_end_matches(['\n', '\r']);
+    (text, &line[text.len()..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structs::init_regexes;
+
+    #[test]
+    fn check_callout_headers() {
+        let callout = init_regexes().callout;
+
+        for (line, fixed) in [
+            ("> [!NOTE]", "> [!NOTE]"),
+            ("> ![NOTE]", "> [!NOTE]"),
+            ("> [NOTE]", "> [!NOTE]"),
+            ("> [NOTE]!", "> [!NOTE]"),
+            ("> [!important] ", "> [!IMPORTANT]"),
+            (">[!Tip]", "> [!TIP]"),
+            (">> [! warning ]", "> > [!WARNING]"),
+        ] {
+            assert_eq!(
+                normalise_callout(line, &callout).as_deref(),
+                Some(fixed)
+            );
+        }
+
+        for line in ["[!NOTE]", "> [!NOTES]", "> Some [NOTE] text", "text"] {
+            assert_eq!(normalise_callout(line, &callout), None);
+        }
+    }
+
+    #[test]
+    fn check_normalise_callouts() {
+        let callout = init_regexes().callout;
+        let contents = "# Day\n\n> ![NOTE]\n> Stuff\r\n\n> [!TIP]\n> Idea";
+
+        let (normalised, fixes) = normalise_callouts(contents, &callout);
+        assert_eq!(
+            normalised,
+            "# Day\n\n> [!NOTE]\n> Stuff\r\n\n> [!TIP]\n> Idea"
+        );
+        assert_eq!(
+            fixes,
+            [Fix {
+                line: 3,
+                original: "> ![NOTE]".to_string(),
+                fixed: "> [!NOTE]".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn check_fenced_callouts() {
+        let callout = init_regexes().callout;
+        let contents = "\
+> [!note]
+> ```md
+> > [note]
+> ```
+
+~~~~
+> ![TIP]
+```
+> [!tip]
+~~~~
+> [tip]
+";
+
+        let (normalised, fixes) = normalise_callouts(contents, &callout);
+        assert_eq!(
+            normalised,
+            contents
+                .replace("> [!note]", "> [!NOTE]")
+                .replace("> [tip]\n", "> [!TIP]\n")
+        );
+        assert_eq!(
+            fixes
+                .iter()
+                .map(|fix| (fix.line, fix.is_cosmetic()))
+                .collect::<Vec<_>>(),
+            [(1, true), (11, false)]
+        );
+    }
+
+    #[test]
+    fn check_format_contents() {
+        let all_regexes = init_regexes();
+        let format_regexes = init_format_regexes();
+        let contents = "\
+# Day
+
+Prose mentioning AT: 9:00 am stays as is.
+
+- [ ] DEADLINE: 2024-7-1 Write report AT: 11:23 PM
+- [x]  Done  AT: 1:05 pm
+> [!important]
+> AT: 14:30 Standup
+> More details END: 3:00 PM
+> [!important]
+> FOR: 1H 30m Retro AT: 4:00 pm
+
+> Plain quote AT: 9:00 am
+";
+
+        let (formatted, fixes) =
+            format_contents(contents, &all_regexes, &format_regexes);
+        assert_eq!(
+            formatted,
+            "\
+# Day
+
+Prose mentioning AT: 9:00 am stays as is.
+
+- [ ] Write report AT: 11:23 PM DEADLINE: 2024-07-01
+- [x]  Done AT: 01:05 PM
+> [!IMPORTANT]
+> Standup AT: 02:30 PM
+> More details END: 03:00 PM
+> [!IMPORTANT]
+> Retro AT: 04:00 PM FOR: 1h30m
+
+> Plain quote AT: 9:00 am
+"
+        );
+        assert_eq!(
+            fixes.iter().map(|fix| fix.line).collect::<Vec<_>>(),
+            [5, 6, 7, 8, 9, 10, 11]
+        );
+
+        let (again, fixes) =
+            format_contents(&formatted, &all_regexes, &format_regexes);
+        assert_eq!(again, formatted);
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn check_format_layout() {
+        let all_regexes = init_regexes();
+        let format_regexes = init_format_regexes();
+        let contents = "\
+- [ ] Run `make  check`  DEADLINE: 2024-7-1
+
+> [!IMPORTANT]
+> Retro AT: 4:00 pm
+
+> [!IMPORTANT]
+> Standup AT: 9:00 am
+> With the team
+
+
+Between the runs
+
+> [!IMPORTANT]
+> Lunch AT: 12:00 pm
+
+> [!IMPORTANT]
+> Offsite ALL DAY
+Prose AT: 9:00 am
+
+> [!IMPORTANT]
+> Review AT: 3:00 pm
+> [!IMPORTANT]
+> Planning AT: 1:00 pm
+
+```
+> [!IMPORTANT]
+> Lunch AT: 12:00
+- [ ] Not a todo DEADLINE: 2024-7-1
+```
+";
+
+        let (formatted, fixes) =
+            format_contents(contents, &all_regexes, &format_regexes);
+        assert_eq!(
+            formatted,
+            "\
+- [ ] Run `make  check` DEADLINE: 2024-07-01
+
+> [!IMPORTANT]
+> Standup AT: 09:00 AM
+> With the team
+
+> [!IMPORTANT]
+> Retro AT: 04:00 PM
+
+
+Between the runs
+
+> [!IMPORTANT]
+> Lunch AT: 12:00 PM
+
+> [!IMPORTANT]
+> Offsite ALL DAY
+Prose AT: 9:00 am
+
+> [!IMPORTANT]
+> Review AT: 03:00 PM
+> [!IMPORTANT]
+> Planning AT: 01:00 PM
+
+```
+> [!IMPORTANT]
+> Lunch AT: 12:00
+- [ ] Not a todo DEADLINE: 2024-7-1
+```
+"
+        );
+        assert_eq!(
+            fixes.iter().map(|fix| fix.line).collect::<Vec<_>>(),
+            [1, 4, 5, 6, 7, 8, 14, 21, 23]
+        );
+
+        let (again, fixes) =
+            format_contents(&formatted, &all_regexes, &format_regexes);
+        assert_eq!(again, formatted);
+        assert!(fixes.is_empty());
+    }
+}
@@ -1,4 +1,15 @@
+pub mod clock;
 pub mod config;
+pub mod edit;
+pub mod format;
 pub mod parse;
+pub mod pick;
+pub mod query;
+pub mod remind;
+pub mod reschedule;
+pub mod rollover;
+pub mod search;
 pub mod structs;
 pub mod tables;
+pub mod template;
+pub mod tui;
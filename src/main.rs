@@ -5,17 +5,39 @@ use std::{
     str::FromStr,
 };
 
-use calcu_rs::parse::parse_sequence;
-use calcu_rs::tables::{print_comments, print_todos};
+use calcu_rs::clock::{clock_in, clock_out};
+use calcu_rs::edit::{complete_todo, open_in_editor};
+use calcu_rs::format::{format_file, init_format_regexes};
+use calcu_rs::parse::{
+    day_filename, ends_after, parse_date, parse_days, parse_duration,
+    parse_sequence, parse_time,
+};
+use calcu_rs::pick::{copy_to_clipboard, pick, PickAction};
+use calcu_rs::query::{
+    free_slots, schedule_conflicts, schedule_totals, time_totals, TagQuery,
+    TagQueryBuilder, TimeGroup, TodoQueryBuilder, TodoSort, TotalPeriod,
+};
+use calcu_rs::remind::{notifier, Reminders, SystemClock};
+use calcu_rs::reschedule::{reschedule, ItemRef};
+use calcu_rs::rollover::rollover;
+use calcu_rs::search::{search, EntryKind, SearchHit, SearchQuery};
+use calcu_rs::structs::{init_regexes, Diagnostic, TIME_FMT};
+use calcu_rs::tables::{print_comments, print_summary, print_todos};
+use calcu_rs::template::create_day;
+use calcu_rs::tui;
 use calcu_rs::{
-    config::{get_config_path, write_default_config, UpperConfig},
-    tables::print_schedule,
+    config::{
+        get_config_path, write_default_config, ColourChoice, UpperConfig,
+    },
+    tables::{print_event_details, print_schedule},
 };
 
-use chrono::{Days, Local, NaiveDate};
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Days, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use clap::{CommandFactory, Parser, Subcommand};
 use env_logger::Env;
 use log::{error, info, warn};
+use regex::Regex;
 
 /// A command-line journal logger, scheduler and task manager.
 #[derive(Parser, Debug)]
@@ -34,22 +56,206 @@ struct Args {
     /// End date
     #[arg(short, long)]
     end_date: Option<NaiveDate>,
+    /// When to colour the tables. Falls back to the config
+    #[arg(
+        long = "color",
+        value_name = "WHEN",
+        value_enum,
+        alias = "colour",
+        global = true
+    )]
+    colour: Option<ColourChoice>,
+    /// Show the ids that `crs reschedule` takes, like 250106-3fa9
+    #[arg(long, global = true)]
+    ids: bool,
+    /// Only show entries tagged with #TAG. Can be repeated. Commands that
+    /// change the notes don't take it
+    #[arg(long, global = true)]
+    tag: Vec<String>,
+    /// Only show entries with the @CONTEXT. Can be repeated. Commands that
+    /// change the notes don't take it
+    #[arg(long, global = true)]
+    context: Vec<String>,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Shows a list of incomplete todos
-    Todo,
+    Todo {
+        /// Order the todos by the given field
+        #[arg(long, value_enum)]
+        sort: Option<TodoSort>,
+        /// Reverse the order of the todos
+        #[arg(long)]
+        reverse: bool,
+        /// Only show todos whose deadline has passed
+        #[arg(long)]
+        overdue: bool,
+        /// Only show todos due within the given days, e.g. 3d or 1w
+        #[arg(long, value_parser = parse_days)]
+        due_within: Option<Duration>,
+        /// Only show todos without a deadline
+        #[arg(long)]
+        no_deadline: bool,
+        /// Only show todos matching the regex
+        #[arg(long)]
+        grep: Option<Regex>,
+    },
     /// Shows you a schedule of your day
-    Schedule,
+    Schedule {
+        /// Show the description, location and attendees of every event
+        #[arg(long)]
+        details: bool,
+        /// Sum up the scheduled hours per day or week
+        #[arg(
+            long,
+            value_enum,
+            num_args = 0..=1,
+            default_missing_value = "day"
+        )]
+        total: Option<TotalPeriod>,
+        /// Show the events that overlap, end before they start or are still
+        /// open at the end
+        #[arg(long)]
+        conflicts: bool,
+    },
     /// Shows you the logs you record throughout your days
     Logs,
+    /// Shows you the blockers you ran into
+    Blockers,
+    /// Shows you the ideas you jotted down
+    Ideas,
+    /// Searches your notes. Takes filters like type:todo, tag:work,
+    /// tag:@office, before:2024-06-01 and after:2024-05-01
+    Search {
+        /// Text to look for, along with any filters
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Treat the text as a regex
+        #[arg(long)]
+        regex: bool,
+        /// Also search the free text of the notes
+        #[arg(long)]
+        prose: bool,
+    },
+    /// Picks a todo or an event with fuzzy filtering, to mark it done,
+    /// reschedule it, edit it or copy it
+    Pick,
+    /// Browses the todos, schedule and logs of each day full screen, and
+    /// adds, completes and reschedules todos and moves events in place
+    Tui,
+    /// Opens the note of a day in $VISUAL or $EDITOR, creating it if needed,
+    /// and reports the problems in it afterwards
+    Edit {
+        /// Day to edit, like 2024-06-21 or an offset like 1d. Defaults to
+        /// today
+        date: Option<String>,
+    },
+    /// Moves the open todos of the days before into today's note, marking
+    /// the originals as migrated with `- [>]`
+    Rollover {
+        /// Link every moved todo back to the day it came from
+        #[arg(long)]
+        link: bool,
+    },
+    /// Moves the deadline of a todo, or an event to another day or time
+    Reschedule {
+        /// Todo or event to move, as its id like 250621-3fa9 or the day and
+        /// the line it starts on like 2024-06-21:12
+        item: ItemRef,
+        /// New day, like 2024-06-21, 3d, tomorrow or friday
+        #[arg(long)]
+        to: String,
+        /// New start time of an event, like 02:30 PM or 14:30
+        #[arg(long, value_parser = parse_time)]
+        at: Option<NaiveTime>,
+    },
+    /// Sends the reminders of events and deadlines that are due, as the
+    /// config says
+    Remind {
+        /// Keep watching the notes and send every reminder when it is time
+        #[arg(long)]
+        daemon: bool,
+    },
+    /// Shows the free time within your working hours, around every event
+    Free {
+        /// Only look at this day, even outside the working week. Defaults to
+        /// today unless a start date is given
+        #[arg(long)]
+        on: Option<NaiveDate>,
+        /// Shortest free time worth showing, e.g. 45m or 1h30m
+        #[arg(long, value_parser = parse_duration, default_value = "30m")]
+        min: Duration,
+    },
+    /// Tracks the time you spend on a project in today's note
+    Clock {
+        #[command(subcommand)]
+        action: ClockAction,
+    },
+    /// Summarises your notes
+    Report {
+        #[command(subcommand)]
+        report: Report,
+    },
+    /// Rewrites your notes into their canonical layout
+    Fmt {
+        /// Only report the notes that need formatting. Fails if there are any
+        #[arg(long)]
+        check: bool,
+        /// Print the lines that change
+        #[arg(long)]
+        diff: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ClockAction {
+    /// Starts working on the project
+    In {
+        /// Project tag, with or without the leading #
+        project: String,
+    },
+    /// Stops working on the current project
+    Out,
+}
+
+#[derive(Subcommand, Debug)]
+enum Report {
+    /// Shows the time tracked with `crs clock`
+    Time {
+        /// Sum up the time per project or per day
+        #[arg(long, value_enum, default_value_t = TimeGroup::Project)]
+        by: TimeGroup,
+        /// Sum up the time per week
+        #[arg(long)]
+        week: bool,
+    },
 }
 
 fn main() -> Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or("off"))
         .init();
     let args = Args::parse();
+    // These only edit the notes or look at time, not at tagged entries.
+    let filtered = !args.tag.is_empty() || !args.context.is_empty();
+    if filtered
+        && matches!(
+            args.command,
+            Commands::Fmt { .. }
+                | Commands::Clock { .. }
+                | Commands::Edit { .. }
+                | Commands::Rollover { .. }
+                | Commands::Reschedule { .. }
+                | Commands::Free { .. }
+        )
+    {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--tag and --context can't be used with this command",
+            )
+            .exit();
+    }
 
     let config_file = match args.config {
         Some(path) => Ok(path),
@@ -89,21 +295,29 @@ fn main() -> Result<()> {
         })?;
     }
 
-    let config = UpperConfig::try_parse(&config_file).map_err(|e| {
+    let mut config = UpperConfig::try_parse(&config_file).map_err(|e| {
         error!("Error occured in reading config file!");
         error!("{e:?}");
         io::ErrorKind::InvalidData
     })?;
+    if args.ids {
+        config.todos.show_ids = true;
+        config.schedule.show_ids = true;
+        config.comments.show_ids = true;
+        config.blockers.show_ids = true;
+        config.ideas.show_ids = true;
+    }
+
+    // Every command agrees on the day, in the zone of the config.
+    let now = Utc::now().with_timezone(&config.timezone());
+    let today = now.date_naive();
 
     let start_date = args.start_date.unwrap_or(config.start_date);
     let end_date = args.end_date.unwrap_or(
-        Local::now()
-            .date_naive()
-            .checked_add_days(Days::new(1))
-            .unwrap_or_else(|| {
-                error!("How far in the future are you using this??");
-                panic!();
-            }),
+        today.checked_add_days(Days::new(1)).unwrap_or_else(|| {
+            error!("How far in the future are you using this??");
+            panic!();
+        }),
     );
     if start_date > end_date {
         error!("Invalid start and end dates. Start date falls later than the end date.");
@@ -114,7 +328,61 @@ fn main() -> Result<()> {
         info!("Notes not provided through cli. Falling back to config");
         PathBuf::from_str(&config.notes_folder).unwrap()
     });
-    let schedule = parse_sequence(&start_date, &end_date, &mut notes);
+    if let Commands::Fmt { check, diff } = args.command {
+        return format_notes(&start_date, &end_date, &notes, check, diff);
+    }
+    if let Commands::Clock { action } = args.command {
+        return clock(action, &mut notes, &config, now);
+    }
+    if let Commands::Edit { date } = args.command {
+        return edit_day(date.as_deref(), &mut notes, &config, today);
+    }
+    if let Commands::Rollover { link } = args.command {
+        return rollover_todos(&notes, &config, link, today);
+    }
+    if let Commands::Reschedule { item, to, at } = args.command {
+        return reschedule_item(&notes, &config, item, &to, at, today);
+    }
+    let filter = TagQueryBuilder::default()
+        .tags(args.tag)
+        .contexts(args.context)
+        .build()
+        .unwrap();
+    if let Commands::Remind { daemon } = args.command {
+        return remind(notes, &config, daemon, filter);
+    }
+    if let Commands::Tui = args.command {
+        let colour = args.colour.unwrap_or(config.colour).enabled();
+        return tui::run(&config, notes, today, colour, filter).map_err(|e| {
+            error!("The terminal interface failed");
+            error!("{e:?}");
+            eprintln!("{e}");
+            Error::from(io::ErrorKind::Other)
+        });
+    }
+
+    let (start_date, end_date) = match args.command {
+        Commands::Free { on: Some(day), .. } => {
+            (day, day.succ_opt().expect("Not the end of time"))
+        }
+        Commands::Free { .. } if args.start_date.is_none() => {
+            (today, today.succ_opt().expect("Not the end of time"))
+        }
+        _ => (start_date, end_date),
+    };
+
+    let mut schedule = parse_sequence(
+        &start_date,
+        &end_date,
+        &mut notes,
+        &config.callouts,
+        &config.timezone(),
+    );
+    report_diagnostics(&schedule.diagnostics, false);
+    filter.apply(&mut schedule);
+    if config.strip_tags {
+        schedule.strip_tags(&init_regexes().tags);
+    }
 
     let size = termsize::get().unwrap_or_else(|| {
         warn!("Terminal size not found.");
@@ -122,17 +390,428 @@ fn main() -> Result<()> {
         termsize::Size { rows: 0, cols: 80 }
     });
 
+    let colour = args.colour.unwrap_or(config.colour).enabled();
+    let later_ends = || {
+        ends_after(
+            &end_date,
+            &mut notes.clone(),
+            &config.callouts,
+            &config.timezone(),
+        )
+    };
+
     match args.command {
-        Commands::Todo => {
-            print_todos(&schedule.tbd_todos, &config.todos, size.cols)
+        Commands::Todo {
+            sort,
+            reverse,
+            overdue,
+            due_within,
+            no_deadline,
+            grep,
+        } => {
+            TodoQueryBuilder::default()
+                .sort(sort)
+                .reverse(reverse)
+                .overdue(overdue)
+                .due_within(due_within)
+                .no_deadline(no_deadline)
+                .grep(grep)
+                .build()
+                .unwrap()
+                .apply(&mut schedule.tbd_todos, today);
+            print_todos(
+                &schedule.tbd_todos,
+                &config.todos,
+                size.cols,
+                colour,
+                today,
+            )
+        }
+        Commands::Schedule { conflicts, .. } if conflicts => print_summary(
+            &schedule_conflicts(&schedule.events, &later_ends()),
+            &config.schedule,
+            size.cols,
+            colour,
+        ),
+        Commands::Schedule {
+            total: Some(period),
+            ..
+        } => print_summary(
+            &schedule_totals(&schedule.events, period),
+            &config.schedule,
+            size.cols,
+            colour,
+        ),
+        Commands::Schedule { details, .. } if details => print_event_details(
+            &schedule.events,
+            &config.schedule,
+            size.cols,
+            colour,
+            config.strip_tags,
+        ),
+        Commands::Schedule { .. } => {
+            print_schedule(
+                &schedule.events,
+                &config.schedule,
+                size.cols,
+                colour,
+                config.strip_tags,
+            );
+            let conflicts =
+                schedule_conflicts(&schedule.events, &later_ends()).len();
+            if conflicts > 0 {
+                eprintln!(
+                    "Found {conflicts} conflicts in your schedule. Run `crs schedule --conflicts` to see them."
+                );
+            }
+        }
+        Commands::Logs => print_comments(
+            &schedule.comments,
+            &config.comments,
+            size.cols,
+            colour,
+        ),
+        Commands::Blockers => print_comments(
+            &schedule.blockers,
+            &config.blockers,
+            size.cols,
+            colour,
+        ),
+        Commands::Ideas => {
+            print_comments(&schedule.ideas, &config.ideas, size.cols, colour)
+        }
+        Commands::Search {
+            query,
+            regex,
+            prose,
+        } => {
+            let query =
+                SearchQuery::parse(&query.join(" "), regex).map_err(|e| {
+                    error!("Invalid search");
+                    error!("{e:?}");
+                    eprintln!("{e}");
+                    io::ErrorKind::InvalidInput
+                })?;
+            let hits = search(&schedule, &query, prose, &init_regexes().tags);
+            print_summary(&hits, &config.schedule, size.cols, colour)
+        }
+        Commands::Pick => {
+            let query = SearchQuery {
+                kinds: vec![EntryKind::Todo, EntryKind::Event],
+                ..SearchQuery::default()
+            };
+            let entries =
+                search(&schedule, &query, false, &init_regexes().tags);
+            pick_entry(entries, &notes, &config, size, today).map_err(|e| {
+                error!("Failed to pick an entry");
+                error!("{e:?}");
+                eprintln!("{e}");
+                io::ErrorKind::Other
+            })?;
+        }
+        Commands::Free { on, min } => {
+            let point_length = parse_duration(
+                &config.working_hours.point_length,
+            )
+            .map_err(|e| {
+                error!("Invalid point length in the config");
+                error!("{e:?}");
+                eprintln!("{e}");
+                io::ErrorKind::InvalidInput
+            })?;
+            let timezone = config.timezone();
+            let slots: Vec<_> = start_date
+                .iter_days()
+                .take_while(|date| *date < end_date)
+                .flat_map(|date| {
+                    free_slots(
+                        &schedule.events,
+                        date,
+                        &config.working_hours,
+                        min,
+                        point_length,
+                        schedule.timezones.get(&date).unwrap_or(&timezone),
+                        on.is_some(),
+                    )
+                })
+                .collect();
+            print_summary(&slots, &config.schedule, size.cols, colour)
+        }
+        Commands::Report {
+            report: Report::Time { by, week },
+        } => print_summary(
+            &time_totals(&schedule.sessions, by, week),
+            &config.schedule,
+            size.cols,
+            colour,
+        ),
+        Commands::Fmt { .. } => unreachable!("Formatting returns early"),
+        Commands::Clock { .. } => unreachable!("Clocking returns early"),
+        Commands::Edit { .. } => unreachable!("Editing returns early"),
+        Commands::Rollover { .. } => unreachable!("Rolling over returns early"),
+        Commands::Reschedule { .. } => {
+            unreachable!("Rescheduling returns early")
+        }
+        Commands::Remind { .. } => unreachable!("Reminding returns early"),
+        Commands::Tui => unreachable!("The interface returns early"),
+    }
+
+    Ok(())
+}
+
+/// Logs every problem found in the notes. Only the ones `crs fmt` can't fix
+/// are printed, unless `all` is set.
+fn report_diagnostics(diagnostics: &[Diagnostic], all: bool) {
+    for diagnostic in diagnostics {
+        warn!(
+            "{} line {}: {}",
+            diagnostic.date, diagnostic.line, diagnostic.message
+        );
+        if all || !diagnostic.fixable {
+            eprintln!(
+                "{} line {}: {}",
+                diagnostic.date, diagnostic.line, diagnostic.message
+            );
+        }
+    }
+    let fixable = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.fixable)
+        .count();
+    if fixable > 0 {
+        eprintln!(
+            "Found {fixable} fixable problems in your notes. Run `crs fmt` to fix them."
+        );
+    }
+}
+
+fn edit_day(
+    date: Option<&str>,
+    notes: &mut PathBuf,
+    config: &UpperConfig,
+    today: NaiveDate,
+) -> Result<()> {
+    let date = match date {
+        Some(input) => parse_date(input, today).ok_or_else(|| {
+            error!("Not a date: {input}");
+            eprintln!("Not a date: {input}");
+            Error::from(io::ErrorKind::InvalidInput)
+        })?,
+        None => today,
+    };
+
+    let path = notes.join(day_filename(&date));
+    create_day(notes, date, config, &[])
+        .and_then(|created| {
+            if created {
+                info!("Created {}", path.display());
+            }
+            open_in_editor(&path, None)
+        })
+        .map_err(|e| {
+            error!("Failed to edit {}", path.display());
+            error!("{e:?}");
+            eprintln!("{e}");
+            Error::from(io::ErrorKind::Other)
+        })?;
+
+    let schedule = parse_sequence(
+        &date,
+        &date.succ_opt().expect("Not the end of time"),
+        notes,
+        &config.callouts,
+        &config.timezone(),
+    );
+    report_diagnostics(&schedule.diagnostics, true);
+    Ok(())
+}
+
+fn rollover_todos(
+    notes: &Path,
+    config: &UpperConfig,
+    link: bool,
+    today: NaiveDate,
+) -> Result<()> {
+    let rolled = rollover(notes, today, config, link).map_err(|e| {
+        error!("Failed to roll the todos over");
+        error!("{e:?}");
+        eprintln!("{e}");
+        Error::from(io::ErrorKind::Other)
+    })?;
+    for todo in &rolled {
+        println!("{}: {}", todo.date, todo.todo);
+    }
+    println!("Moved {} todos into {}", rolled.len(), day_filename(&today));
+    Ok(())
+}
+
+fn reschedule_item(
+    notes: &Path,
+    config: &UpperConfig,
+    item: ItemRef,
+    to: &str,
+    at: Option<NaiveTime>,
+    today: NaiveDate,
+) -> Result<()> {
+    let Some(date) = parse_date(to, today) else {
+        error!("Not a date: {to}");
+        eprintln!("Not a date: {to}");
+        return Err(Error::from(io::ErrorKind::InvalidInput));
+    };
+
+    let name = reschedule(notes, &item, date, at, config).map_err(|e| {
+        error!("Failed to reschedule {item}");
+        error!("{e:?}");
+        eprintln!("{e}");
+        Error::from(io::ErrorKind::InvalidInput)
+    })?;
+    match at {
+        Some(time) => {
+            println!("Moved {name} to {date} {}", time.format(TIME_FMT.fmt))
+        }
+        None => println!("Moved {name} to {date}"),
+    }
+    Ok(())
+}
+
+fn remind(
+    notes: PathBuf,
+    config: &UpperConfig,
+    daemon: bool,
+    filter: TagQuery,
+) -> Result<()> {
+    notifier(&config.reminders)
+        .and_then(|notifier| {
+            let mut reminders =
+                Reminders::new(config, notes, SystemClock, notifier, filter)?;
+            if daemon {
+                reminders.run()
+            } else {
+                reminders.tick().map(|_| ())
+            }
+        })
+        .map_err(|e| {
+            error!("Failed to send reminders");
+            error!("{e:?}");
+            eprintln!("{e}");
+            Error::from(io::ErrorKind::Other)
+        })
+}
+
+fn pick_entry(
+    entries: Vec<SearchHit>,
+    notes: &Path,
+    config: &UpperConfig,
+    size: termsize::Size,
+    today: NaiveDate,
+) -> anyhow::Result<()> {
+    let Some((entry, action)) = pick(entries, today, size.cols, size.rows)?
+    else {
+        return Ok(());
+    };
+
+    let path = notes.join(day_filename(&entry.date));
+    match action {
+        PickAction::Done => {
+            complete_todo(&path, entry.line)?;
+            println!("Done: {}", entry.text);
+        }
+        PickAction::Reschedule(date) => {
+            let item = ItemRef::Line {
+                date: entry.date,
+                line: entry.line,
+            };
+            let name = reschedule(notes, &item, date, None, config)?;
+            println!("Moved {name} to {date}");
+        }
+        PickAction::Edit => open_in_editor(&path, Some(entry.line))?,
+        PickAction::Copy => copy_to_clipboard(&entry.text)?,
+    }
+    Ok(())
+}
+
+fn clock(
+    action: ClockAction,
+    notes: &mut PathBuf,
+    config: &UpperConfig,
+    now: DateTime<Tz>,
+) -> Result<()> {
+    let timezone = config.timezone();
+    let today = now.date_naive();
+    // From yesterday, to clock out of a session that crossed midnight.
+    let sessions = parse_sequence(
+        &today.pred_opt().expect("Not the start of time"),
+        &today.succ_opt().expect("Not the end of time"),
+        notes,
+        &config.callouts,
+        &timezone,
+    )
+    .sessions;
+
+    let path = notes.join(day_filename(&today));
+    match action {
+        ClockAction::In { project } => create_day(notes, today, config, &[])
+            .and_then(|_| clock_in(&path, &sessions, &project, now.time())),
+        ClockAction::Out => clock_out(&path, &sessions, now.time()),
+    }
+    .map_err(|e| {
+        error!("Failed to clock {}", path.display());
+        error!("{e:?}");
+        eprintln!("{e}");
+        Error::from(io::ErrorKind::InvalidInput)
+    })
+}
+
+fn format_notes(
+    start_date: &NaiveDate,
+    end_date: &NaiveDate,
+    notes: &Path,
+    check: bool,
+    diff: bool,
+) -> Result<()> {
+    let all_regexes = init_regexes();
+    let format_regexes = init_format_regexes();
+    let mut unformatted = 0;
+
+    for date in start_date.iter_days().take_while(|date| date < end_date) {
+        let path = notes.join(day_filename(&date));
+        if !path.exists() {
+            continue;
+        }
+
+        let fixes = format_file(&path, &all_regexes, &format_regexes, check)
+            .map_err(|e| {
+                error!("Failed to format {}", path.display());
+                error!("{e:?}");
+                io::ErrorKind::InvalidData
+            })?;
+        if fixes.is_empty() {
+            continue;
         }
-        Commands::Schedule => {
-            print_schedule(&schedule.events, &config.schedule, size.cols)
+
+        unformatted += 1;
+        if check {
+            println!("{} needs formatting", path.display());
+        } else {
+            println!("Formatted {}", path.display());
         }
-        Commands::Logs => {
-            print_comments(&schedule.comments, &config.comments, size.cols)
+        if diff {
+            for fix in fixes {
+                println!("@@ {}:{} @@", path.display(), fix.line);
+                println!("-{}", fix.original);
+                println!("+{}", fix.fixed);
+            }
         }
     }
 
+    if check && unformatted > 0 {
+        let message = format!(
+            "{unformatted} notes need formatting. Run `crs fmt` to fix them."
+        );
+        error!("{message}");
+        eprintln!("{message}");
+        return Err(Error::from(io::ErrorKind::InvalidData));
+    }
+
     Ok(())
 }
@@ -1,35 +1,45 @@
-use std::{collections::HashMap, fs, iter::Peekable, path::PathBuf};
+use std::{
+    cell::Cell, collections::HashSet, fs, iter::Peekable, ops::Range,
+    path::PathBuf,
+};
 
-use crate::structs::{self, TimeInterval, DATE_FMT};
+use crate::config::{CalloutConfig, EntryType};
+use crate::format;
+use crate::structs::{self, localise, TimeInterval, DATE_FMT};
 
-use anyhow::{Context, Result};
-use chrono::{NaiveDate, NaiveTime};
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Days, Duration, NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
 use log::{info, trace, warn};
-use pulldown_cmark::{BlockQuoteKind, Event, Options, Parser, Tag, TagEnd};
+use pulldown_cmark::{
+    DefaultBrokenLinkCallback, Event, MetadataBlockKind, OffsetIter, Options,
+    Parser, Tag, TagEnd,
+};
 use regex::Regex;
 
 pub fn parse_sequence(
     start_date: &NaiveDate,
     end_date: &NaiveDate,
     path: &mut PathBuf,
+    callouts: &CalloutConfig,
+    timezone: &Tz,
 ) -> structs::Schedule {
     let mut day_iter = start_date.iter_days().peekable();
 
-    let tbd_todos = Vec::new();
-    let events = HashMap::new();
-    let comments = Vec::new();
-
     let all_regexes = structs::init_regexes();
 
-    let mut sched = structs::Schedule {
-        events,
-        comments,
-        tbd_todos,
-    };
+    let mut sched = structs::Schedule::default();
 
     while day_iter.peek().expect("Impossible") != end_date {
         let date = day_iter.next().expect("Impossible unless overflow.");
-        if let Err(e) = parse_one_day(&date, path, &all_regexes, &mut sched) {
+        if let Err(e) = parse_one_day(
+            &date,
+            path,
+            &all_regexes,
+            callouts,
+            timezone,
+            &mut sched,
+        ) {
             warn!(
                 "No file exists at the specified path with the name {}",
                 date.format(DATE_FMT.fmt)
@@ -43,107 +53,549 @@ pub fn parse_sequence(
     sched
 }
 
+/// The events that end after `from` without starting there, by the day
+/// they end on. Reads every note from `from` on, so that events still open
+/// at the end of a range can be told from the ones without an end.
+pub fn ends_after(
+    from: &NaiveDate,
+    path: &mut PathBuf,
+    callouts: &CalloutConfig,
+    timezone: &Tz,
+) -> Vec<(NaiveDate, String)> {
+    let last = fs::read_dir(&path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let day = name.to_str()?.strip_suffix(".md")?;
+            NaiveDate::parse_from_str(day, DATE_FMT.fmt).ok()
+        })
+        .max();
+    let Some(last) = last.filter(|last| last >= from) else {
+        return Vec::new();
+    };
+    parse_sequence(
+        from,
+        &last.succ_opt().expect("Not the end of time"),
+        path,
+        callouts,
+        timezone,
+    )
+    .stray_ends
+}
+
+/// Name of the note for the given day, relative to the notes folder.
+pub fn day_filename(date: &NaiveDate) -> String {
+    format!("{}.md", date.format(DATE_FMT.fmt))
+}
+
 pub fn parse_one_day(
     date: &NaiveDate,
     path: &mut PathBuf,
     all_regexes: &structs::AllRegexes,
+    callouts: &CalloutConfig,
+    timezone: &Tz,
     sched: &mut structs::Schedule,
 ) -> Result<()> {
-    let filename = format!("{}.md", date.format("%Y-%m-%d"));
-    path.push(filename);
+    path.push(day_filename(date));
 
     let contents = fs::read_to_string(&path).context(
         "Failed to read the file to the string. The file was {filenae}",
     )?;
 
-    let mut parse_stream = Parser::new_ext(
-        &contents,
-        Options::ENABLE_TASKLISTS | Options::ENABLE_GFM,
-    )
+    let (contents, fixes) =
+        format::normalise_callouts(&contents, &all_regexes.callout);
+    for fix in fixes {
+        if fix.is_cosmetic() {
+            info!(
+                "{date} line {}: {}",
+                fix.line,
+                fix.to_diagnostic(*date).message
+            );
+        } else {
+            sched.diagnostics.push(fix.to_diagnostic(*date));
+        }
+    }
+
+    let offset = Cell::new(0);
+    let mut parse_stream = SourceEvents {
+        events: Parser::new_ext(
+            &contents,
+            Options::ENABLE_TASKLISTS
+                | Options::ENABLE_GFM
+                | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS,
+        )
+        .into_offset_iter(),
+        offset: &offset,
+    }
     .peekable();
+    let line_starts: Vec<usize> = [0]
+        .into_iter()
+        .chain(contents.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+
+    // A `TZ:` line takes precedence over the timezone in the front matter.
+    let tz_keyword = all_regexes
+        .timezone
+        .captures(&contents)
+        .and_then(|cap| parse_timezone(date, &cap[1], sched));
+    let mut timezone = tz_keyword.unwrap_or(*timezone);
+
+    let mut items: Vec<ListItem> = Vec::new();
+    // Indices of the subtasks and of the todos they are nested under, linked
+    // by id once the ids are final.
+    let mut subtasks: Vec<(usize, usize)> = Vec::new();
+
+    let mut prose: Option<structs::Prose> = None;
 
     while let Some(content) = parse_stream.next() {
+        // Nothing was peeked at since, so this is where `content` starts.
+        let line = line_starts.partition_point(|start| *start <= offset.get());
         match content {
-            Event::TaskListMarker(done) if !done => {
+            Event::Start(Tag::MetadataBlock(MetadataBlockKind::YamlStyle)) => {
+                trace!("Front matter encountered!");
+                parse_front_matter(date, sched, &mut parse_stream);
+                let front_matter_tz = sched
+                    .days
+                    .get(date)
+                    .and_then(|meta| meta.timezone.clone())
+                    .and_then(|name| parse_timezone(date, &name, sched));
+                if let (None, Some(tz)) = (tz_keyword, front_matter_tz) {
+                    timezone = tz;
+                }
+            }
+            Event::Start(Tag::Item) => {
+                sched.prose.extend(prose.take());
+                items.push(ListItem::default());
+            }
+            Event::End(TagEnd::Item) => {
+                sched.prose.extend(prose.take());
+                if let Some(item) = items.pop() {
+                    finish_item(item, &mut items, &mut sched.tbd_todos);
+                }
+            }
+            Event::TaskListMarker(done) => {
+                let depth =
+                    items.iter().filter(|item| item.done.is_some()).count();
+                let parent = items
+                    .iter()
+                    .rev()
+                    .find(|item| item.done.is_some())
+                    .and_then(|item| item.todo);
+                if let Some(item) = items.last_mut() {
+                    item.done = Some(done);
+                }
+                if done {
+                    continue;
+                }
+
                 trace!("Incomplete task encountered!");
+                let index = sched.tbd_todos.len();
                 parse_tasks(
                     &mut sched.tbd_todos,
                     &mut parse_stream,
-                    &all_regexes.deadline,
-                    &all_regexes.at_time,
+                    all_regexes,
                     date,
-                )
+                    line,
+                    depth,
+                );
+                if sched.tbd_todos.len() > index {
+                    if let Some(item) = items.last_mut() {
+                        item.todo = Some(index);
+                    }
+                    subtasks.extend(parent.map(|parent| (index, parent)));
+                }
             }
-            Event::Start(Tag::BlockQuote(Some(BlockQuoteKind::Note))) => {
-                trace!("Comment with Info blockquote encountered!");
-                parse_comments(
-                    date,
-                    &mut sched.comments,
-                    &all_regexes.at_time,
-                    &mut parse_stream,
-                )
+            Event::Start(Tag::BlockQuote(Some(kind))) => {
+                match callouts.entry_type(kind) {
+                    EntryType::Log => {
+                        trace!("Comment with {kind:?} blockquote encountered!");
+                        parse_comments(
+                            date,
+                            &mut sched.comments,
+                            &all_regexes.at_time,
+                            &all_regexes.tags,
+                            &timezone,
+                            line,
+                            &mut parse_stream,
+                        )
+                    }
+                    EntryType::Schedule => {
+                        trace!(
+                            "Schedule with {kind:?} blockquote encountered!"
+                        );
+                        parse_schedule(
+                            sched,
+                            all_regexes,
+                            &mut parse_stream,
+                            date,
+                            line,
+                            &timezone,
+                        );
+                    }
+                    EntryType::Blocker => {
+                        trace!("Blocker with {kind:?} blockquote encountered!");
+                        parse_comments(
+                            date,
+                            &mut sched.blockers,
+                            &all_regexes.at_time,
+                            &all_regexes.tags,
+                            &timezone,
+                            line,
+                            &mut parse_stream,
+                        )
+                    }
+                    EntryType::Idea => {
+                        trace!("Idea with {kind:?} blockquote encountered!");
+                        parse_comments(
+                            date,
+                            &mut sched.ideas,
+                            &all_regexes.at_time,
+                            &all_regexes.tags,
+                            &timezone,
+                            line,
+                            &mut parse_stream,
+                        )
+                    }
+                    EntryType::Ignore => skip_block_quote(&mut parse_stream),
+                }
             }
-            Event::Start(Tag::BlockQuote(Some(BlockQuoteKind::Important))) => {
-                trace!("Schedule with Important blockquote encountered!");
-                parse_schedule(
-                    &mut sched.events,
-                    &all_regexes.at_time,
-                    &all_regexes.end,
-                    &all_regexes.all_day,
-                    &mut parse_stream,
-                    date,
-                );
+            Event::Start(Tag::Paragraph | Tag::Heading { .. }) => {
+                sched.prose.extend(prose.take());
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_)) => {
+                sched.prose.extend(prose.take());
+            }
+            // Done tasks are neither todos nor prose.
+            Event::Text(_) | Event::Code(_)
+                if items.last().is_some_and(|item| item.done.is_some()) =>
+            {
+                continue;
+            }
+            Event::Text(text) | Event::Code(text) => {
+                prose
+                    .get_or_insert_with(|| structs::Prose {
+                        date: *date,
+                        line,
+                        text: String::new(),
+                    })
+                    .text
+                    .push_str(&text);
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(prose) = prose.as_mut() {
+                    prose.text.push(' ');
+                }
             }
             _ => continue,
         }
     }
+    sched.prose.extend(prose);
+
+    parse_clock(date, &contents, all_regexes, &timezone, sched);
+    sched.timezones.insert(*date, timezone);
+    dedupe_ids(date, sched);
+    for (index, parent) in subtasks {
+        sched.tbd_todos[index].parent =
+            Some(sched.tbd_todos[parent].id.clone());
+    }
     Ok(())
 }
 
+/// Gives every entry of the day that hashes to an id already taken one of
+/// its own, in the order they were written.
+fn dedupe_ids(date: &NaiveDate, sched: &mut structs::Schedule) {
+    let mut taken = HashSet::new();
+    let mut claim = |id: &mut String, kind: &str, content: &str| {
+        let mut occurrence = 1;
+        while !taken.insert(id.clone()) {
+            occurrence += 1;
+            *id = structs::item_id(
+                date,
+                kind,
+                &format!("{content}\n{occurrence}"),
+            );
+        }
+    };
+
+    let mut events: Vec<_> = sched
+        .events
+        .iter_mut()
+        .filter(|(_, event)| event.start_time.0 .0 == *date)
+        .collect();
+    events.sort_by_key(|(_, event)| event.line);
+    for (name, event) in events {
+        claim(&mut event.id, "event", name);
+    }
+    for todo in sched.tbd_todos.iter_mut().filter(|todo| todo.date == *date) {
+        claim(&mut todo.id, "todo", &todo.todo);
+    }
+    for comment in sched
+        .comments
+        .iter_mut()
+        .chain(sched.blockers.iter_mut())
+        .chain(sched.ideas.iter_mut())
+        .filter(|comment| comment.date == *date)
+    {
+        claim(&mut comment.id, "comment", &comment.comment);
+    }
+}
+
+/// Pairs every `CLOCK IN:` line with the `CLOCK OUT:` line following it.
+/// A session without one is left running.
+fn parse_clock(
+    date: &NaiveDate,
+    contents: &str,
+    all_regexes: &structs::AllRegexes,
+    timezone: &Tz,
+    sched: &mut structs::Schedule,
+) {
+    for cap in all_regexes.clock.captures_iter(contents) {
+        let time = localise(
+            timezone,
+            date.and_time(
+                NaiveTime::parse_from_str(&cap[2], structs::TIME_FMT.fmt)
+                    .unwrap_or_default(),
+            ),
+        );
+        // Sessions clocked out of the day after they started crossed midnight.
+        let open = sched.sessions.last_mut().filter(|session| {
+            session.end.is_none()
+                && (session.date == *date
+                    || &cap[1] == "OUT"
+                        && session.date.succ_opt() == Some(*date))
+        });
+
+        match (&cap[1], open) {
+            ("IN", Some(session)) => {
+                warn!("Clocked in again on {date} without clocking out");
+                session.end = Some(time);
+            }
+            ("OUT", Some(session)) => {
+                trace!("Clocked out");
+                session.end = Some(time);
+                continue;
+            }
+            ("OUT", None) => {
+                let line = contents
+                    [..cap.get(0).expect("Always present").start()]
+                    .lines()
+                    .count()
+                    + 1;
+                sched.diagnostics.push(structs::Diagnostic {
+                    date: *date,
+                    line,
+                    message: "Clocked out without clocking in".to_string(),
+                    fixable: false,
+                });
+                continue;
+            }
+            _ => (),
+        }
+
+        trace!("Clocked in");
+        // The project is the rest of the line, so that it may have spaces,
+        // and any tags after it are only tags.
+        let tags = structs::find_tags(&cap[3], &all_regexes.tags);
+        let rest = cap[3].trim();
+        let project = structs::strip_tags(
+            rest.strip_prefix('#').unwrap_or(rest),
+            &all_regexes.tags,
+        );
+        let project = (!project.is_empty()).then_some(project);
+        sched.sessions.push(structs::WorkSession {
+            date: *date,
+            project,
+            start: time,
+            end: None,
+            tags,
+        });
+    }
+}
+
+fn parse_front_matter(
+    date: &NaiveDate,
+    sched: &mut structs::Schedule,
+    parse_stream: &mut Peekable<SourceEvents>,
+) {
+    let mut yaml = String::new();
+    while let Some(Event::Text(node)) = parse_stream
+        .next_if(|event| !matches!(event, Event::End(TagEnd::MetadataBlock(_))))
+    {
+        yaml.push_str(&node);
+    }
+
+    if yaml.trim().is_empty() {
+        return;
+    }
+    match serde_yaml_ng::from_str::<structs::DayMeta>(&yaml) {
+        Ok(meta) => {
+            trace!("Parsed the front matter");
+            sched.days.insert(*date, meta);
+        }
+        Err(e) => {
+            warn!("Failed to parse the front matter of {date}: {e}");
+            sched.diagnostics.push(structs::Diagnostic {
+                date: *date,
+                line: e.location().map_or(1, |location| location.line() + 1),
+                message: format!("Invalid front matter: {e}"),
+                fixable: false,
+            });
+        }
+    }
+}
+
+fn parse_timezone(
+    date: &NaiveDate,
+    name: &str,
+    sched: &mut structs::Schedule,
+) -> Option<Tz> {
+    match name.parse() {
+        Ok(tz) => Some(tz),
+        Err(e) => {
+            warn!("Unknown timezone {name} in {date}: {e}");
+            sched.diagnostics.push(structs::Diagnostic {
+                date: *date,
+                line: 1,
+                message: format!("Unknown timezone {name}"),
+                fixable: false,
+            });
+            None
+        }
+    }
+}
+
+/// Events of a day file. Keeps track of where the event last pulled from the
+/// parser starts, which is the one last returned or peeked at.
+struct SourceEvents<'a> {
+    events: OffsetIter<'a, DefaultBrokenLinkCallback>,
+    offset: &'a Cell<usize>,
+}
+
+impl<'a> Iterator for SourceEvents<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (event, Range { start, .. }) = self.events.next()?;
+        self.offset.set(start);
+        Some(event)
+    }
+}
+
+/// A list item that is still open while walking through a day file.
+#[derive(Default)]
+struct ListItem {
+    /// Set once the item turns out to be a task.
+    done: Option<bool>,
+    /// Index of the todo parsed from the item, if it was incomplete.
+    todo: Option<usize>,
+    subtasks_done: usize,
+    subtasks_total: usize,
+}
+
+fn finish_item(
+    item: ListItem,
+    open_items: &mut [ListItem],
+    tbd: &mut [structs::ToDo],
+) {
+    let Some(done) = item.done else {
+        return;
+    };
+
+    if let Some(parent) =
+        open_items.iter_mut().rev().find(|item| item.done.is_some())
+    {
+        parent.subtasks_total += 1;
+        if done {
+            parent.subtasks_done += 1;
+        }
+    }
+
+    if let Some(todo) = item.todo.and_then(|index| tbd.get_mut(index)) {
+        if item.subtasks_total > 0 {
+            todo.progress = Some((item.subtasks_done, item.subtasks_total));
+        }
+    }
+}
+
 fn parse_schedule(
-    events: &mut HashMap<String, structs::CalEvent>,
-    start_search: &Regex,
-    end_search: &Regex,
-    all_day_search: &Regex,
-    parse_stream: &mut Peekable<Parser>,
+    sched: &mut structs::Schedule,
+    all_regexes: &structs::AllRegexes,
+    parse_stream: &mut Peekable<SourceEvents>,
     date: &NaiveDate,
+    line: usize,
+    timezone: &Tz,
 ) {
-    let mut content = String::new();
+    let start_search = &all_regexes.at_time;
+    let end_search = &all_regexes.end;
+    let all_day_search = &all_regexes.all_day;
+    let mut lines = vec![String::new()];
 
     trace!("Reading content from the schedule block");
     while parse_stream.peek() != Some(&Event::End(TagEnd::BlockQuote)) {
-        if let Some(Event::Text(node)) = parse_stream.next() {
-            content.push_str(&node);
-            trim_in_place(&mut content);
-            content.push(' ');
+        match parse_stream.next() {
+            Some(Event::Text(node) | Event::Code(node)) => {
+                lines.last_mut().expect("Never empty").push_str(&node);
+            }
+            Some(
+                Event::SoftBreak
+                | Event::HardBreak
+                | Event::End(TagEnd::Paragraph),
+            ) => lines.push(String::new()),
+            _ => (),
         }
     }
-    trim_in_place(&mut content);
+    lines.iter_mut().for_each(trim_in_place);
+    lines.retain(|line| !line.trim().is_empty());
+    if lines.is_empty() {
+        return;
+    }
+
+    let content = lines.join(" ");
+    let tags = structs::find_tags(&content, &all_regexes.tags);
+    let title = lines.remove(0);
+    let details = parse_event_details(&lines, all_regexes);
 
     let start_time = start_search.find(&content);
     if let Some(time) = start_time {
         trace!("Block was a schedule beginning");
 
-        let mut name = content.replace(time.as_str(), "");
-        trim_in_place(&mut name);
-        let time_interval = (
-            TimeInterval((
-                *date,
-                Some(
-                    NaiveTime::parse_from_str(
-                        time.as_str().split_once(':').unwrap().1.trim(),
-                        structs::TIME_FMT.fmt,
-                    )
-                    .unwrap_or_default(),
-                ),
-            )),
-            TimeInterval((*date, None)),
+        let mut name = title.replace(time.as_str(), "");
+        let start = localise(
+            timezone,
+            date.and_time(
+                NaiveTime::parse_from_str(
+                    time.as_str().split_once(':').unwrap().1.trim(),
+                    structs::TIME_FMT.fmt,
+                )
+                .unwrap_or_default(),
+            ),
         );
-        events.insert(
+
+        // `FOR:` stands in for a separate END block.
+        let mut end = TimeInterval((*date, None));
+        if let Some(cap) = all_regexes.for_duration.captures(&content) {
+            name = name.replace(&cap[0], "");
+            if let Ok(duration) = parse_duration(&cap[1]) {
+                let end_time = start + duration;
+                end = TimeInterval((end_time.date_naive(), Some(end_time)));
+            }
+        }
+        trim_in_place(&mut name);
+        let time_interval = (TimeInterval((*date, Some(start))), end);
+        let id = structs::item_id(date, "event", &name);
+        sched.events.insert(
             name,
             structs::CalEvent {
                 start_time: time_interval.0,
                 end_time: time_interval.1,
+                tags,
+                description: details.0,
+                location: details.1,
+                with: details.2,
+                line,
+                id,
             },
         );
         return;
@@ -153,15 +605,22 @@ fn parse_schedule(
     if let Some(time) = all_day {
         trace!("Block was an all day schedule");
 
-        let mut name = content.replace(time.as_str(), "");
+        let mut name = title.replace(time.as_str(), "");
         trim_in_place(&mut name);
         let time_interval =
             (TimeInterval((*date, None)), TimeInterval((*date, None)));
-        events.insert(
+        let id = structs::item_id(date, "event", &name);
+        sched.events.insert(
             name,
             structs::CalEvent {
                 start_time: time_interval.0,
                 end_time: time_interval.1,
+                tags,
+                description: details.0,
+                location: details.1,
+                with: details.2,
+                line,
+                id,
             },
         );
         return;
@@ -170,87 +629,223 @@ fn parse_schedule(
     let end_time = end_search.find(&content);
     if let Some(time) = end_time {
         trace!("Block was a schedule end");
-        let mut name = content.replace(time.as_str(), "");
+        let mut name = title.replace(time.as_str(), "");
         trim_in_place(&mut name);
-        let cal_event = match events.get_mut(&name) {
-            Some(e) => e,
-            None => panic!(
-                "
-                Error occured while parsing {} in {content}. Please check 
-                that the name you've specified is correct or not.
-                ",
-                name
-            ),
+        let Some(cal_event) = sched.events.get_mut(&name) else {
+            warn!("No event named {name} to end on {date}");
+            sched.diagnostics.push(structs::Diagnostic {
+                date: *date,
+                line,
+                message: format!("END of {name}, which never started"),
+                fixable: false,
+            });
+            sched.stray_ends.push((*date, name));
+            return;
         };
-        cal_event.start_time =
-            TimeInterval((
-                cal_event.start_time.0 .0,
-                Some(
-                    cal_event.start_time.0 .1.unwrap_or(
-                        NaiveTime::from_hms_opt(00, 00, 00).unwrap(),
-                    ),
-                ),
-            ));
+        let start_date = cal_event.start_time.0 .0;
+        cal_event.start_time = TimeInterval((
+            start_date,
+            Some(cal_event.start_time.0 .1.unwrap_or_else(|| {
+                localise(timezone, start_date.and_time(NaiveTime::MIN))
+            })),
+        ));
         cal_event.end_time = TimeInterval((
             *date,
-            Some(
-                NaiveTime::parse_from_str(
-                    time.as_str().split_once(':').unwrap().1.trim(),
-                    structs::TIME_FMT.fmt,
-                )
-                .unwrap_or_default(),
-            ),
+            Some(localise(
+                timezone,
+                date.and_time(
+                    NaiveTime::parse_from_str(
+                        time.as_str().split_once(':').unwrap().1.trim(),
+                        structs::TIME_FMT.fmt,
+                    )
+                    .unwrap_or_default(),
+                ),
+            )),
         ));
     }
 }
 
+/// Splits the lines following the title of a schedule block into the
+/// description, the `LOCATION:` and the comma separated `WITH:` keys.
+fn parse_event_details(
+    lines: &[String],
+    all_regexes: &structs::AllRegexes,
+) -> (String, Option<String>, Vec<String>) {
+    let mut description = Vec::new();
+    let mut location = None;
+    let mut with = Vec::new();
+
+    for line in lines {
+        let mut line = line.clone();
+        for search in [
+            &all_regexes.at_time,
+            &all_regexes.end,
+            &all_regexes.for_duration,
+            &all_regexes.all_day,
+        ] {
+            line = search.replace_all(&line, "").into_owned();
+        }
+        let line = line.trim();
+
+        if let Some(place) = line.strip_prefix("LOCATION:") {
+            location = Some(place.trim().to_string());
+        } else if let Some(people) = line.strip_prefix("WITH:") {
+            with.extend(
+                people
+                    .split(',')
+                    .map(|person| person.trim().to_string())
+                    .filter(|person| !person.is_empty()),
+            );
+        } else if !line.is_empty() {
+            description.push(line.to_string());
+        }
+    }
+
+    (description.join("\n"), location, with)
+}
+
 fn parse_tasks(
     tbd: &mut Vec<structs::ToDo>,
-    parse_stream: &mut Peekable<Parser>,
-    deadline_search: &Regex,
-    time_search: &Regex,
+    parse_stream: &mut Peekable<SourceEvents>,
+    all_regexes: &structs::AllRegexes,
     date: &NaiveDate,
+    line: usize,
+    depth: usize,
 ) {
-    if let Some(Event::Text(node)) = parse_stream.peek() {
-        let deadline = deadline_search.find(node);
-        let time_of_write = time_search.find(node);
+    let (plain, markdown) = collect_inline(parse_stream);
+    if plain.trim().is_empty() {
+        return;
+    }
+
+    let tags = structs::find_tags(&plain, &all_regexes.tags);
+    let deadline = all_regexes.deadline.find(&plain);
+    let time_of_write = all_regexes.at_time.find(&plain);
+    let priority = all_regexes.priority.captures(&plain);
 
-        let todo = node
+    let strip = |content: &str| {
+        let content = content
             .replace(deadline.map_or("", |date| date.into()), "")
             .replace(time_of_write.map_or("", |time| time.into()), "");
-
-        let deadline = deadline.map(|date| {
-            NaiveDate::parse_from_str(
-                date.as_str().split_once(':').expect("Impossible").1.trim(),
-                structs::DATE_FMT.fmt,
+        // The marker is looked up again, as it sits elsewhere in markdown.
+        match all_regexes.priority.find(&content) {
+            Some(found) => format!(
+                "{} {}",
+                content[..found.start()].trim_end(),
+                content[found.end()..].trim_start()
             )
-            .unwrap_or_default()
-        });
+            .trim()
+            .to_string(),
+            None => content.trim().to_string(),
+        }
+    };
+    let todo = strip(&plain);
+    let markdown = strip(&markdown);
 
-        let time_of_write = time_of_write.map(|time| {
-            NaiveTime::parse_from_str(
-                time.as_str().split_once(':').expect("Impossible").1.trim(),
-                structs::TIME_FMT.fmt,
-            )
-            .unwrap_or_default()
-        });
+    let priority = priority.and_then(|cap| {
+        cap.get(1)
+            .or_else(|| cap.get(2))
+            .and_then(|level| level.as_str().parse().ok())
+    });
 
-        trace!("Parsed a TODO");
-        let task = structs::ToDo {
-            date: *date,
-            time_of_write,
-            todo,
-            deadline,
-        };
-        tbd.push(task);
+    let deadline = deadline.map(|date| {
+        NaiveDate::parse_from_str(
+            date.as_str().split_once(':').expect("Impossible").1.trim(),
+            structs::DATE_FMT.fmt,
+        )
+        .unwrap_or_default()
+    });
+
+    let time_of_write = time_of_write.map(|time| {
+        NaiveTime::parse_from_str(
+            time.as_str().split_once(':').expect("Impossible").1.trim(),
+            structs::TIME_FMT.fmt,
+        )
+        .unwrap_or_default()
+    });
+
+    trace!("Parsed a TODO");
+    let id = structs::item_id(date, "todo", &todo);
+    let task = structs::ToDo {
+        date: *date,
+        time_of_write,
+        todo,
+        markdown,
+        deadline,
+        priority,
+        tags,
+        depth,
+        progress: None,
+        parent: None,
+        line,
+        id,
+    };
+    tbd.push(task);
+}
+
+/// Reads the inline content following a task marker, stopping before the
+/// end of the list item or any nested block. Returns the content as plain
+/// text and as markdown.
+fn collect_inline(
+    parse_stream: &mut Peekable<SourceEvents>,
+) -> (String, String) {
+    let mut plain = String::new();
+    let mut markdown = String::new();
+    let mut links = Vec::new();
+
+    loop {
+        match parse_stream.peek() {
+            Some(Event::Text(text)) => {
+                plain.push_str(text);
+                markdown.push_str(text);
+            }
+            Some(Event::Code(code)) => {
+                plain.push_str(code);
+                markdown.push_str(&format!("`{code}`"));
+            }
+            Some(Event::InlineHtml(html)) => markdown.push_str(html),
+            Some(Event::SoftBreak | Event::HardBreak) => {
+                plain.push(' ');
+                markdown.push('\n');
+            }
+            Some(Event::Start(Tag::Paragraph)) => (),
+            Some(Event::End(TagEnd::Paragraph)) => {
+                plain.push(' ');
+                markdown.push_str("\n\n");
+            }
+            Some(
+                Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis),
+            ) => markdown.push('*'),
+            Some(Event::Start(Tag::Strong) | Event::End(TagEnd::Strong)) => {
+                markdown.push_str("**")
+            }
+            Some(
+                Event::Start(Tag::Strikethrough)
+                | Event::End(TagEnd::Strikethrough),
+            ) => markdown.push_str("~~"),
+            Some(Event::Start(Tag::Link { dest_url, .. })) => {
+                links.push(dest_url.to_string());
+                markdown.push('[');
+            }
+            Some(Event::End(TagEnd::Link)) => {
+                let url = links.pop().unwrap_or_default();
+                markdown.push_str(&format!("]({url})"));
+            }
+            _ => break,
+        }
+        parse_stream.next();
     }
+
+    (plain, markdown)
 }
 
 fn parse_comments(
     date: &NaiveDate,
     comments: &mut Vec<structs::Comment>,
     time_search: &Regex,
-    parse_stream: &mut Peekable<Parser>,
+    tag_search: &Regex,
+    timezone: &Tz,
+    line: usize,
+    parse_stream: &mut Peekable<SourceEvents>,
 ) {
     let mut comment = String::new();
     while parse_stream.peek() != Some(&Event::End(TagEnd::BlockQuote)) {
@@ -260,25 +855,125 @@ fn parse_comments(
         }
     }
 
+    // Callouts without a time are kept, as blockers and ideas often have none.
     let time = time_search.find(&comment);
-    if let Some(time) = time {
-        let comment = comment.replace(time.as_str(), "");
-        let time_of_write = date.and_time(
-            NaiveTime::parse_from_str(
-                time.as_str().split_once(':').expect("Impossible").1.trim(),
-                structs::TIME_FMT.fmt,
-            )
-            .unwrap_or_default(),
-        );
+    let time_of_write = time.map(|time| {
+        localise(
+            timezone,
+            date.and_time(
+                NaiveTime::parse_from_str(
+                    time.as_str().split_once(':').expect("Impossible").1.trim(),
+                    structs::TIME_FMT.fmt,
+                )
+                .unwrap_or_default(),
+            ),
+        )
+    });
+    let comment = comment.replace(time.map_or("", |time| time.as_str()), "");
+    let tags = structs::find_tags(&comment, tag_search);
 
-        trace!("Parsed a comment");
-        comments.push(structs::Comment {
-            time_of_write,
-            comment,
-        })
+    trace!("Parsed a comment");
+    comments.push(structs::Comment {
+        date: *date,
+        time_of_write,
+        id: structs::item_id(date, "comment", &comment),
+        comment,
+        tags,
+        line,
+    })
+}
+
+/// Skips the rest of a block quote, including the quotes nested in it.
+fn skip_block_quote(parse_stream: &mut Peekable<SourceEvents>) {
+    let mut depth = 1;
+    for content in parse_stream.by_ref() {
+        match content {
+            Event::Start(Tag::BlockQuote(_)) => depth += 1,
+            Event::End(TagEnd::BlockQuote) if depth == 1 => return,
+            Event::End(TagEnd::BlockQuote) => depth -= 1,
+            _ => (),
+        }
     }
 }
 
+/// Parses durations like `3d`, `1w2d` or `1h 30m`. Supported units are weeks,
+/// days, hours and minutes.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+
+    for chr in input.chars().filter(|chr| !chr.is_whitespace()) {
+        if chr.is_ascii_digit() {
+            number.push(chr);
+            continue;
+        }
+
+        let amount: i64 = number
+            .parse()
+            .with_context(|| format!("Missing amount in duration {input}"))?;
+        let part = match chr {
+            'w' => Duration::try_weeks(amount),
+            'd' => Duration::try_days(amount),
+            'h' => Duration::try_hours(amount),
+            'm' => Duration::try_minutes(amount),
+            _ => bail!("Unknown unit {chr} in duration {input}"),
+        };
+        duration = part
+            .and_then(|part| duration.checked_add(&part))
+            .with_context(|| format!("Duration {input} is too long"))?;
+        number.clear();
+    }
+
+    if !number.is_empty() || duration.is_zero() {
+        bail!("Duration {input} is missing a unit");
+    }
+    Ok(duration)
+}
+
+/// Reads a duration of at least a day, as deadlines have no time of the day.
+pub fn parse_days(input: &str) -> Result<Duration> {
+    let duration = parse_duration(input)?;
+    if duration < Duration::days(1) {
+        bail!("Deadlines are whole days, so {input} must be at least 1d");
+    }
+    Ok(duration)
+}
+
+/// Reads a date like `2024-06-21`, an offset from today like `3d`, `today`,
+/// `tomorrow` or a weekday like `friday`, which is the next one after today.
+pub fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim();
+    match input.to_lowercase().as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        _ => (),
+    }
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        let ahead = (weekday.num_days_from_monday() + 6
+            - today.weekday().num_days_from_monday())
+            % 7
+            + 1;
+        return today.checked_add_days(Days::new(ahead.into()));
+    }
+    input
+        .parse()
+        .ok()
+        .or_else(|| today.checked_add_signed(parse_duration(input).ok()?))
+}
+
+/// Reads a time like `02:30 PM`, `2:30pm`, `2pm` or `14:30`.
+pub fn parse_time(input: &str) -> Result<NaiveTime> {
+    let mut input = input.trim().to_uppercase();
+    if !input.contains(':') {
+        let hours = input.find(|c: char| !c.is_ascii_digit());
+        input.insert_str(hours.unwrap_or(input.len()), ":00");
+    }
+    ["%I:%M %p", "%I:%M%p", "%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(&input, fmt).ok())
+        .with_context(|| format!("Invalid time {input}"))
+}
+
 fn trim_in_place(content: &mut String) {
     while content.ends_with(' ')
         || content.ends_with('\t')
@@ -297,22 +992,552 @@ mod test {
     fn check_file_parser_single() {
         let date = NaiveDate::from_ymd_opt(2024, 6, 19).unwrap();
         let all_regexes = structs::init_regexes();
-        let mut sched = structs::Schedule {
-            events: HashMap::new(),
-            comments: Vec::new(),
-            tbd_todos: Vec::new(),
-        };
+        let mut sched = structs::Schedule::default();
 
         assert!(parse_one_day(
             &date,
             PathBuf::from_str("tests").as_mut().unwrap(),
             &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
             &mut sched,
         )
         .is_ok());
         println!("{:#?}", sched);
     }
 
+    #[test]
+    fn check_tags() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        assert_eq!(sched.tbd_todos[0].tags, ["#work", "@office"]);
+        assert_eq!(sched.tbd_todos[1].tags, ["@home"]);
+        assert_eq!(sched.tbd_todos[2].tags, ["#work"]);
+        assert_eq!(sched.comments[0].tags, ["#calcurs"]);
+        let standup = &sched.events["Standup #work @office"];
+        assert_eq!(standup.tags, ["#work", "@office"]);
+
+        sched.strip_tags(&all_regexes.tags);
+        assert_eq!(sched.tbd_todos[0].todo, "Ship the release");
+        assert_eq!(sched.comments[0].comment, "Reviewed the backlog");
+        assert!(sched.events.contains_key("Standup #work @office"));
+    }
+
+    #[test]
+    fn check_priorities() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let priorities: Vec<_> =
+            sched.tbd_todos.iter().map(|todo| todo.priority).collect();
+        assert_eq!(
+            priorities,
+            [
+                None,
+                None,
+                Some(structs::Priority::High),
+                Some(structs::Priority::Low)
+            ]
+        );
+        assert_eq!(sched.tbd_todos[2].todo, "Fix the flaky test #work");
+        assert_eq!(sched.tbd_todos[3].todo, "Sort the bookshelf");
+
+        let marker = &all_regexes.priority;
+        assert_eq!(&marker.captures("(B)").unwrap()[1], "B");
+        assert!(!marker.is_match("(A)ffordable housing"));
+    }
+
+    #[test]
+    fn check_subtasks() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 7).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let todos: Vec<_> = sched
+            .tbd_todos
+            .iter()
+            .map(|todo| (todo.todo.trim(), todo.depth, todo.progress))
+            .collect();
+        assert_eq!(
+            todos,
+            [
+                ("Plan the trip", 0, Some((2, 4))),
+                ("Book hotel", 1, Some((1, 2))),
+                ("Check reviews", 2, None),
+                ("Pack", 1, None),
+                ("Standalone task", 0, None),
+            ]
+        );
+
+        let todos = &sched.tbd_todos;
+        let parents: Vec<_> =
+            todos.iter().map(|todo| todo.parent.as_ref()).collect();
+        assert_eq!(
+            parents,
+            [
+                None,
+                Some(&todos[0].id),
+                Some(&todos[1].id),
+                Some(&todos[0].id),
+                None
+            ]
+        );
+
+        let prose: Vec<_> = sched
+            .prose
+            .iter()
+            .map(|prose| prose.text.as_str())
+            .collect();
+        assert_eq!(prose, ["Nested tasks", "Not a task"]);
+    }
+
+    #[test]
+    fn check_inline_formatting() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 8).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let review = &sched.tbd_todos[0];
+        assert_eq!(review.todo, "Review PR for issue");
+        assert_eq!(
+            review.markdown,
+            "Review **PR** for [issue](https://example.com)"
+        );
+        assert_eq!(review.deadline, NaiveDate::from_ymd_opt(2025, 1, 10));
+
+        let multi_line = &sched.tbd_todos[1];
+        assert_eq!(multi_line.todo, "Run cargo test on the branch and fix it");
+        assert_eq!(
+            multi_line.markdown,
+            "Run `cargo test` on the *branch*\nand fix it"
+        );
+        assert_eq!(sched.tbd_todos[2].todo, "Child");
+    }
+
+    #[test]
+    fn check_event_details() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 9).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let review = &sched.events["Design review"];
+        assert_eq!(
+            review.start_time.1.map(|time| time.time()),
+            NaiveTime::from_hms_opt(14, 0, 0)
+        );
+        assert_eq!(
+            review.end_time.1.map(|time| time.time()),
+            NaiveTime::from_hms_opt(15, 0, 0)
+        );
+        assert_eq!(
+            review.description,
+            "Walk through the new parser.\nBring the benchmarks."
+        );
+        assert_eq!(review.location.as_deref(), Some("Room 4"));
+        assert_eq!(review.with, ["Asha", "Ben"]);
+
+        let pairing = &sched.events["Pairing session"];
+        assert_eq!(
+            pairing.end_time.1.map(|time| time.time()),
+            NaiveTime::from_hms_opt(11, 30, 0)
+        );
+        assert_eq!(pairing.duration(), Some(Duration::minutes(90)));
+        assert!(pairing.description.is_empty());
+        assert_eq!(pairing.with, ["Ben"]);
+
+        let offsite = &sched.events["Offsite"];
+        assert!(offsite.start_time.1.is_none());
+        assert!(offsite.description.is_empty());
+        assert_eq!(offsite.location.as_deref(), Some("Lakeside"));
+    }
+
+    #[test]
+    fn check_callout_mapping() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        let all_regexes = structs::init_regexes();
+
+        let mut sched = structs::Schedule::default();
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+        assert_eq!(sched.comments.len(), 1);
+        assert_eq!(sched.ideas.len(), 1);
+        assert_eq!(sched.blockers.len(), 4);
+        assert_eq!(sched.ideas[0].comment.trim(), "Cache the regexes");
+        assert_eq!(
+            sched.blockers[2].comment.trim(),
+            "Blocked on the design review"
+        );
+        assert_eq!(sched.blockers[2].time_of_write, None);
+
+        let callouts = crate::config::CalloutConfigBuilder::default()
+            .tip(EntryType::Log)
+            .caution(EntryType::Ignore)
+            .build()
+            .unwrap();
+        let mut sched = structs::Schedule::default();
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &callouts,
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+        assert_eq!(sched.comments.len(), 2);
+        assert!(sched.ideas.is_empty());
+        assert_eq!(sched.blockers.len(), 2);
+        assert!(sched.tbd_todos.is_empty());
+    }
+
+    #[test]
+    fn check_lenient_callouts() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let swapped = sched
+            .diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.line == 3)
+            .unwrap();
+        assert!(swapped.fixable);
+        assert!(swapped.message.contains("> ![NOTE]"));
+        assert!(sched.events.contains_key("Stuff that is"));
+    }
+
+    #[test]
+    fn check_front_matter() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 11).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let meta = &sched.days[&date];
+        assert_eq!(meta.mood.as_deref(), Some("focused"));
+        assert_eq!(meta.location.as_deref(), Some("Lisbon"));
+        assert_eq!(meta.tags, ["travel", "work"]);
+        assert_eq!(meta.timezone.as_deref(), Some("Europe/Lisbon"));
+        assert!(meta.extra.contains_key("weather"));
+
+        assert_eq!(sched.tbd_todos.len(), 1);
+        assert_eq!(sched.tbd_todos[0].todo, "Buy a travel adapter");
+        assert_eq!(
+            sched.comments[0].time_of_write.unwrap().timezone(),
+            chrono_tz::Europe::Lisbon
+        );
+
+        for (yaml, tags) in [
+            ("tags: work", vec!["work"]),
+            ("tags: work, travel", vec!["work", "travel"]),
+            ("tags: [work]", vec!["work"]),
+            ("mood: fine", vec![]),
+        ] {
+            let meta: structs::DayMeta = serde_yaml_ng::from_str(yaml).unwrap();
+            assert_eq!(meta.tags, tags);
+        }
+    }
+
+    #[test]
+    fn check_timezone_keyword() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 12).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        let flight = sched.events["Flight home"].start_time.1.unwrap();
+        assert_eq!(flight.timezone(), chrono_tz::Asia::Tokyo);
+        assert_eq!(sched.timezones[&date], chrono_tz::Asia::Tokyo);
+        assert_eq!(
+            flight.naive_utc(),
+            NaiveDate::from_ymd_opt(2025, 1, 12)
+                .unwrap()
+                .and_hms_opt(1, 30, 0)
+                .unwrap()
+        );
+        let note = &sched.comments[0];
+        assert_eq!(
+            note.time_of_write.unwrap().timezone(),
+            chrono_tz::Asia::Tokyo
+        );
+    }
+
+    #[test]
+    fn check_clock() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 13).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sched
+                .sessions
+                .iter()
+                .map(|session| (
+                    session.project.as_deref(),
+                    session.duration().map(|duration| duration.num_minutes())
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (Some("calcu-rs"), Some(90)),
+                (Some("docs"), Some(60)),
+                (Some("calcu-rs"), Some(45))
+            ]
+        );
+        assert_eq!(sched.tbd_todos.len(), 1);
+        assert_eq!(sched.diagnostics.len(), 1);
+        assert_eq!(sched.diagnostics[0].line, 3);
+    }
+
+    #[test]
+    fn check_fenced_code() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        assert!(sched.diagnostics.is_empty());
+        assert!(sched.tbd_todos.is_empty());
+        assert_eq!(sched.comments.len(), 1);
+    }
+
+    #[test]
+    fn check_end_without_start() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 14).unwrap();
+        let all_regexes = structs::init_regexes();
+        let mut sched = structs::Schedule::default();
+
+        parse_one_day(
+            &date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &all_regexes,
+            &CalloutConfig::default(),
+            &Tz::UTC,
+            &mut sched,
+        )
+        .unwrap();
+
+        assert_eq!(sched.events.len(), 3);
+        assert!(!sched.events.contains_key("Retro"));
+        assert_eq!(sched.diagnostics.len(), 1);
+        assert_eq!(sched.diagnostics[0].line, 12);
+        assert!(!sched.diagnostics[0].fixable);
+    }
+
+    #[test]
+    fn check_ends_after() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().to_path_buf();
+        let day = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        fs::write(
+            notes.join("2025-01-06.md"),
+            "> [!IMPORTANT]\n> Offsite AT: 09:00 AM\n",
+        )
+        .unwrap();
+        fs::write(
+            notes.join("2025-01-08.md"),
+            "> [!IMPORTANT]\n> Lunch AT: 12:00 PM\n\n\
+             > [!IMPORTANT]\n> Lunch END: 01:00 PM\n\n\
+             > [!IMPORTANT]\n> Offsite END: 05:00 PM\n",
+        )
+        .unwrap();
+        fs::write(notes.join("notes.md"), "Not a day\n").unwrap();
+
+        let ends = |from| {
+            ends_after(
+                &from,
+                &mut notes.clone(),
+                &CalloutConfig::default(),
+                &Tz::UTC,
+            )
+        };
+        assert_eq!(ends(day(7)), [(day(8), "Offsite".to_string())]);
+        assert!(ends(day(9)).is_empty());
+    }
+
+    #[test]
+    fn check_item_ids() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut notes = dir.path().to_path_buf();
+        let ids = |notes: &mut PathBuf| {
+            let sched = parse_sequence(
+                &date,
+                &date.succ_opt().unwrap(),
+                notes,
+                &CalloutConfig::default(),
+                &Tz::UTC,
+            );
+            sched
+                .tbd_todos
+                .iter()
+                .map(|todo| todo.id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let path = notes.join("2025-01-06.md");
+        fs::write(
+            &path,
+            "- [ ] Call mum\n- [ ] Water plants\n- [ ] Call mum\n",
+        )
+        .unwrap();
+        let before = ids(&mut notes);
+        assert_eq!(before[0], structs::item_id(&date, "todo", "Call mum"));
+        assert!(before[0].starts_with("250106-"));
+        assert_ne!(before[0], before[2]);
+
+        fs::write(
+            &path,
+            "# Monday\n\n- [ ] Call mum\n- [ ] Water plants DEADLINE: 2025-01-09\n- [ ] Call mum\n",
+        )
+        .unwrap();
+        assert_eq!(ids(&mut notes), before);
+    }
+
+    #[test]
+    fn check_duration_parser() {
+        assert_eq!(parse_duration("3d").unwrap(), Duration::days(3));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("1h 30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("1w2d").unwrap(), Duration::days(9));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("3x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("99999999999999w").is_err());
+        assert!(parse_days("12h").is_err());
+        assert_eq!(parse_days("1w").unwrap(), Duration::days(7));
+    }
+
+    #[test]
+    fn check_date_and_time_parsers() {
+        let day = |day| NaiveDate::from_ymd_opt(2025, 1, day);
+        let today = day(8).unwrap();
+        assert_eq!(
+            parse_date("2025-02-01", today),
+            NaiveDate::from_ymd_opt(2025, 2, 1)
+        );
+        assert_eq!(parse_date("3d", today), day(11));
+        assert_eq!(parse_date("Tomorrow", today), day(9));
+        assert_eq!(parse_date("friday", today), day(10));
+        assert_eq!(parse_date("Tue", today), day(14));
+        assert_eq!(parse_date("wed", today), day(15));
+        assert_eq!(parse_date("someday", today), None);
+        assert_eq!(parse_date("99999999w", today), None);
+
+        let half_two = NaiveTime::from_hms_opt(14, 30, 0);
+        assert_eq!(parse_time("02:30 PM").ok(), half_two);
+        assert_eq!(parse_time("2:30pm").ok(), half_two);
+        assert_eq!(parse_time("14:30").ok(), half_two);
+        assert_eq!(parse_time("2pm").ok(), NaiveTime::from_hms_opt(14, 0, 0));
+        assert!(parse_time("half two").is_err());
+    }
+
     #[test]
     fn check_file_parser_range() {
         let start_date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
@@ -322,6 +1547,8 @@ mod test {
             &start_date,
             &end_date,
             PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
         );
         println!("{:#?}", sched);
     }
@@ -0,0 +1,450 @@
+use std::io::{self, Write};
+
+use crate::parse::parse_date;
+use crate::search::{EntryKind, SearchHit};
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDate;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+
+/// What to do with the entry that was picked.
+#[derive(Debug, PartialEq)]
+pub enum PickAction {
+    Done,
+    Reschedule(NaiveDate),
+    Edit,
+    Copy,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome<'a> {
+    Pending,
+    Picked(&'a SearchHit, PickAction),
+    Quit,
+}
+
+/// Incremental fuzzy filtering over a list of entries.
+#[derive(Debug)]
+pub struct Picker {
+    entries: Vec<SearchHit>,
+    query: String,
+    /// Indices into `entries` of the ones matching the query, best first.
+    matches: Vec<usize>,
+    selected: usize,
+    /// The date typed in so far while rescheduling.
+    date: Option<String>,
+    /// Shown in place of the key help until the next key.
+    message: Option<String>,
+    today: NaiveDate,
+}
+
+impl Picker {
+    pub fn new(entries: Vec<SearchHit>, today: NaiveDate) -> Self {
+        let mut picker = Picker {
+            entries,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            date: None,
+            message: None,
+            today,
+        };
+        picker.filter();
+        picker
+    }
+
+    pub fn selection(&self) -> Option<&SearchHit> {
+        self.matches
+            .get(self.selected)
+            .map(|index| &self.entries[*index])
+    }
+
+    fn filter(&mut self) {
+        let mut scored: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                Some((fuzzy_score(&self.query, &entry.text)?, index))
+            })
+            .collect();
+        // Stable, so equally good matches keep the order of the notes.
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+
+    /// Handles a key press, which may pick the selected entry.
+    pub fn handle(&mut self, key: KeyEvent) -> Outcome<'_> {
+        self.message = None;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        if let Some(date) = self.date.as_mut() {
+            match key.code {
+                KeyCode::Esc => self.date = None,
+                KeyCode::Backspace => {
+                    date.pop();
+                }
+                KeyCode::Enter => {
+                    let typed = self.date.take().unwrap_or_default();
+                    match parse_date(&typed, self.today) {
+                        Some(date) => {
+                            return self.pick(PickAction::Reschedule(date))
+                        }
+                        None => {
+                            self.message = Some(format!("Not a date: {typed}"))
+                        }
+                    }
+                }
+                KeyCode::Char(chr) if !ctrl => date.push(chr),
+                _ => (),
+            }
+            return Outcome::Pending;
+        }
+
+        let kind = self.selection().map(|entry| entry.kind);
+        let is_todo = kind == Some(EntryKind::Todo);
+        let is_event = kind == Some(EntryKind::Event);
+        match key.code {
+            KeyCode::Esc => return Outcome::Quit,
+            KeyCode::Char('c') if ctrl => return Outcome::Quit,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Down => self.select_next(),
+            KeyCode::Char('n') if ctrl => self.select_next(),
+            KeyCode::Enter => return self.pick(PickAction::Edit),
+            KeyCode::Char('y') if ctrl => return self.pick(PickAction::Copy),
+            KeyCode::Char('d') if ctrl && is_todo => {
+                return self.pick(PickAction::Done)
+            }
+            KeyCode::Char('r') if ctrl && (is_todo || is_event) => {
+                self.date = Some(String::new())
+            }
+            KeyCode::Char('d') if ctrl => {
+                self.message = Some("Only works on todos".to_string())
+            }
+            KeyCode::Char('r') if ctrl => {
+                self.message =
+                    Some("Only works on todos and events".to_string())
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            KeyCode::Char(chr) if !ctrl => {
+                self.query.push(chr);
+                self.filter();
+            }
+            _ => (),
+        }
+        Outcome::Pending
+    }
+
+    fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    fn pick(&self, action: PickAction) -> Outcome<'_> {
+        match self.selection() {
+            Some(entry) => Outcome::Picked(entry, action),
+            None => Outcome::Pending,
+        }
+    }
+
+    /// Lines to draw, cut to the width and height of the terminal.
+    pub fn render(&self, width: u16, height: u16) -> Vec<(String, bool)> {
+        let width = width as usize;
+        let mut lines = vec![
+            match &self.date {
+                Some(date) => (format!("Reschedule to: {date}"), false),
+                None => (format!("> {}", self.query), false),
+            },
+            (
+                self.message.clone().unwrap_or(format!(
+                    "{}/{}  ↑↓ move  enter edit  ^d done  ^r reschedule  ^y copy  esc quit",
+                    self.matches.len(),
+                    self.entries.len()
+                )),
+                false,
+            ),
+        ];
+
+        let rows = (height as usize).saturating_sub(lines.len()).max(1);
+        let skip = (self.selected + 1).saturating_sub(rows);
+        for (row, index) in
+            self.matches.iter().enumerate().skip(skip).take(rows)
+        {
+            let entry = &self.entries[*index];
+            lines.push((
+                format!(
+                    "{} {:>4} {:<5} {}",
+                    entry.date,
+                    entry.line,
+                    entry.kind,
+                    entry.text.replace('\n', " ")
+                ),
+                row == self.selected,
+            ));
+        }
+
+        lines
+            .into_iter()
+            .map(|(line, selected)| {
+                (line.chars().take(width).collect(), selected)
+            })
+            .collect()
+    }
+}
+
+/// Scores how well the text matches the characters of the query, in order
+/// and ignoring case. Consecutive characters and ones at the start of a word
+/// score higher. Returns `None` if the text does not match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut position = 0;
+
+    for wanted in query
+        .to_lowercase()
+        .chars()
+        .filter(|chr| !chr.is_whitespace())
+    {
+        let found = position
+            + text[position..].iter().position(|chr| *chr == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - position).min(10) as i64 / 2;
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Runs the picker until an entry is picked or the user quits.
+pub fn pick(
+    entries: Vec<SearchHit>,
+    today: NaiveDate,
+    width: u16,
+    height: u16,
+) -> Result<Option<(SearchHit, PickAction)>> {
+    let mut stdout = io::stdout();
+    let mut picker = Picker::new(entries, today);
+    // Some terminals report no size at all.
+    let (width, height) = match (width, height) {
+        (0, _) | (_, 0) => (80, 24),
+        size => size,
+    };
+
+    let screen = Screen::enter()?;
+    let picked = (|| -> Result<Option<(usize, PickAction)>> {
+        loop {
+            queue!(stdout, terminal::Clear(ClearType::All))?;
+            for (row, (line, selected)) in
+                picker.render(width, height).into_iter().enumerate()
+            {
+                queue!(stdout, cursor::MoveTo(0, row as u16))?;
+                if selected {
+                    queue!(
+                        stdout,
+                        SetAttribute(Attribute::Reverse),
+                        Print(line),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    queue!(stdout, Print(line))?;
+                }
+            }
+            stdout.flush()?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let selected = picker.matches.get(picker.selected).copied();
+            match picker.handle(key) {
+                Outcome::Picked(_, action) => {
+                    return Ok(selected.map(|index| (index, action)))
+                }
+                Outcome::Pending => (),
+                Outcome::Quit => return Ok(None),
+            }
+        }
+    })();
+    drop(screen);
+
+    Ok(picked?
+        .map(|(index, action)| (picker.entries.swap_remove(index), action)))
+}
+
+/// Raw mode on the alternate screen, left again when dropped, even if
+/// the picker fails or panics.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        // Nothing is left to report an error to.
+        let _ = execute!(
+            io::stdout(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Copies the text to the clipboard of the terminal with an OSC 52 escape
+/// sequence, which also works over ssh.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(line: usize, kind: EntryKind, text: &str) -> SearchHit {
+        SearchHit {
+            date: NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(),
+            line,
+            kind,
+            text: text.to_string(),
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(chr: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(chr), KeyModifiers::CONTROL)
+    }
+
+    fn texts(picker: &Picker) -> Vec<&str> {
+        picker
+            .matches
+            .iter()
+            .map(|index| picker.entries[*index].text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn check_fuzzy_score() {
+        assert!(fuzzy_score("", "anything").is_some());
+        assert!(fuzzy_score("shp", "Ship the release").is_some());
+        assert!(fuzzy_score("psh", "Ship the release").is_none());
+        assert!(
+            fuzzy_score("ship", "Ship the release")
+                > fuzzy_score("ship", "Sort the bookshelf pile")
+        );
+        assert!(
+            fuzzy_score("tp", "Water the plants")
+                > fuzzy_score("tp", "Buy stamps")
+        );
+    }
+
+    #[test]
+    fn check_picker() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let mut picker = Picker::new(
+            vec![
+                entry(3, EntryKind::Todo, "Ship the release"),
+                entry(5, EntryKind::Todo, "Water the plants"),
+                entry(11, EntryKind::Event, "Standup"),
+            ],
+            today,
+        );
+        assert_eq!(texts(&picker).len(), 3);
+
+        for chr in "st".chars() {
+            assert_eq!(
+                picker.handle(key(KeyCode::Char(chr))),
+                Outcome::Pending
+            );
+        }
+        assert_eq!(texts(&picker), ["Standup", "Ship the release"]);
+
+        let standup = entry(11, EntryKind::Event, "Standup");
+        assert_eq!(
+            picker.handle(key(KeyCode::Enter)),
+            Outcome::Picked(&standup, PickAction::Edit)
+        );
+
+        assert_eq!(picker.handle(ctrl('d')), Outcome::Pending);
+        assert_eq!(picker.render(80, 10)[1].0, "Only works on todos");
+        picker.handle(ctrl('r'));
+        assert_eq!(picker.render(80, 10)[0].0, "Reschedule to: ");
+        picker.handle(key(KeyCode::Esc));
+
+        picker.handle(key(KeyCode::Down));
+        picker.handle(ctrl('r'));
+        for chr in "2x".chars() {
+            picker.handle(key(KeyCode::Char(chr)));
+        }
+        picker.handle(key(KeyCode::Backspace));
+        picker.handle(key(KeyCode::Char('d')));
+        assert_eq!(picker.render(80, 10)[0].0, "Reschedule to: 2d");
+        let ship = entry(3, EntryKind::Todo, "Ship the release");
+        assert_eq!(
+            picker.handle(key(KeyCode::Enter)),
+            Outcome::Picked(
+                &ship,
+                PickAction::Reschedule(
+                    NaiveDate::from_ymd_opt(2025, 1, 8).unwrap()
+                )
+            )
+        );
+
+        assert_eq!(picker.handle(key(KeyCode::Esc)), Outcome::Quit);
+    }
+
+    #[test]
+    fn check_render() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let mut picker = Picker::new(
+            (1..=10)
+                .map(|line| entry(line, EntryKind::Todo, "A task"))
+                .collect(),
+            today,
+        );
+        for _ in 0..5 {
+            picker.handle(key(KeyCode::Down));
+        }
+
+        let lines = picker.render(20, 5);
+        assert_eq!(lines.len(), 5);
+        assert!(lines.iter().all(|(line, _)| line.chars().count() <= 20));
+        assert_eq!(lines[4], ("2025-01-06    6 Todo".to_string(), true));
+    }
+}
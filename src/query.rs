@@ -0,0 +1,897 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+};
+
+use crate::config::WorkingHoursConfig;
+use crate::structs::{
+    localise, CalEvent, ConflictKind, FreeSlot, Schedule, ScheduleConflict,
+    ScheduleTotal, TimeTotal, ToDo, WorkSession,
+};
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use derive_builder::Builder;
+use regex::Regex;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TodoSort {
+    /// Date of the note the todo was written in
+    Date,
+    /// Deadline of the todo. Todos without one come last
+    Deadline,
+    /// Time of the day the todo was written at
+    TimeOfWrite,
+    /// Priority of the todo, most urgent first. Todos without one come last
+    Priority,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TotalPeriod {
+    Day,
+    /// Weeks starting on Monday
+    Week,
+}
+
+/// Sums up the timed events per day or week of their start, in order. All
+/// day events and events without an end are not counted.
+pub fn schedule_totals(
+    events: &HashMap<String, CalEvent>,
+    period: TotalPeriod,
+) -> Vec<ScheduleTotal> {
+    let mut totals: BTreeMap<NaiveDate, (usize, Duration)> = BTreeMap::new();
+    for event in events.values() {
+        let Some(duration) = event.duration() else {
+            continue;
+        };
+        let date = match period {
+            TotalPeriod::Day => event.start_time.0 .0,
+            TotalPeriod::Week => {
+                event.start_time.0 .0.week(Weekday::Mon).first_day()
+            }
+        };
+        let total = totals.entry(date).or_insert((0, Duration::zero()));
+        total.0 += 1;
+        total.1 += duration;
+    }
+
+    totals
+        .into_iter()
+        .map(|(date, (events, scheduled))| ScheduleTotal {
+            period: match period {
+                TotalPeriod::Day => date.to_string(),
+                TotalPeriod::Week => format!("Week of {date}"),
+            },
+            events,
+            scheduled,
+        })
+        .collect()
+}
+
+/// Finds the timed events of the same day that overlap, the events that end
+/// before they start and the events still open at the end of the range,
+/// whose END block is in `later_ends`. Other events without an end only
+/// clash when they start during another event.
+pub fn schedule_conflicts(
+    events: &HashMap<String, CalEvent>,
+    later_ends: &[(NaiveDate, String)],
+) -> Vec<ScheduleConflict> {
+    let mut conflicts = Vec::new();
+    let mut timed: Vec<_> = events
+        .iter()
+        .filter_map(|(name, event)| Some((name, event.start_time.1?, event)))
+        .collect();
+    timed.sort_by_key(|(name, start, _)| (*start, *name));
+
+    for (index, (name, start, event)) in timed.iter().enumerate() {
+        let date = event.start_time.0 .0;
+        // Events with only a start are points in time, they cannot clash
+        // unless another event runs over them.
+        let Some(end) = event.end_time.1 else {
+            if later_ends.iter().any(|(_, ended)| ended == *name) {
+                conflicts.push(ScheduleConflict {
+                    date,
+                    kind: ConflictKind::Unclosed,
+                    events: vec![name.to_string()],
+                });
+            }
+            continue;
+        };
+        if end < *start {
+            conflicts.push(ScheduleConflict {
+                date,
+                kind: ConflictKind::EndsBeforeStart,
+                events: vec![name.to_string()],
+            });
+            continue;
+        }
+
+        // Sorted by start, so only the later events and the points at the
+        // same start can start before this one ends.
+        for (other_index, (other, other_start, other_event)) in
+            timed.iter().enumerate()
+        {
+            let is_point = other_event.end_time.1.is_none();
+            if other_index == index || (other_index < index && !is_point) {
+                continue;
+            }
+            if other_event.start_time.0 .0 == date
+                && start <= other_start
+                && *other_start < end
+            {
+                conflicts.push(ScheduleConflict {
+                    date,
+                    kind: ConflictKind::Overlap,
+                    events: vec![name.to_string(), other.to_string()],
+                });
+            }
+        }
+    }
+
+    conflicts.sort_by(|a, b| {
+        (a.date, a.kind, &a.events).cmp(&(b.date, b.kind, &b.events))
+    });
+    conflicts
+}
+
+/// Finds the stretches of at least `min` within the working hours of the
+/// date that no timed event takes up. An event without an end takes up
+/// `point_length`. Unless `any_day` is set, days outside the working week
+/// have none.
+pub fn free_slots(
+    events: &HashMap<String, CalEvent>,
+    date: NaiveDate,
+    hours: &WorkingHoursConfig,
+    min: Duration,
+    point_length: Duration,
+    timezone: &Tz,
+    any_day: bool,
+) -> Vec<FreeSlot> {
+    if !any_day && !hours.days.contains(&date.weekday()) {
+        return Vec::new();
+    }
+
+    let day_start = localise(timezone, date.and_time(hours.start));
+    let day_end = localise(timezone, date.and_time(hours.end));
+    let mut busy: Vec<_> = events
+        .values()
+        .filter_map(|event| {
+            let start = event.start_time.1?;
+            Some((start, event.end_time.1.unwrap_or(start + point_length)))
+        })
+        .filter(|(start, end)| *start < day_end && day_start < *end)
+        .collect();
+    busy.sort();
+
+    let mut slots = Vec::new();
+    let mut free_from = day_start;
+    for (start, end) in busy.into_iter().chain([(day_end, day_end)]) {
+        let start = start.with_timezone(timezone).min(day_end);
+        if start - free_from >= min {
+            slots.push(FreeSlot {
+                date,
+                start: free_from,
+                end: start,
+                length: start - free_from,
+            });
+        }
+        free_from = free_from.max(end.with_timezone(timezone));
+    }
+    slots
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TimeGroup {
+    Project,
+    Day,
+}
+
+/// Sums up the finished work sessions per project, or per day across all
+/// projects. With `week` set, the totals are per week instead of over the
+/// whole range or per day.
+pub fn time_totals(
+    sessions: &[WorkSession],
+    by: TimeGroup,
+    week: bool,
+) -> Vec<TimeTotal> {
+    let mut totals: BTreeMap<(String, String), (usize, Duration)> =
+        BTreeMap::new();
+    for session in sessions {
+        let Some(duration) = session.duration() else {
+            continue;
+        };
+        let period = if week {
+            format!("Week of {}", session.date.week(Weekday::Mon).first_day())
+        } else if by == TimeGroup::Day {
+            session.date.to_string()
+        } else {
+            "Total".to_string()
+        };
+        let project = match by {
+            TimeGroup::Project => {
+                session.project.clone().unwrap_or("None".to_string())
+            }
+            TimeGroup::Day => "All".to_string(),
+        };
+        let total = totals
+            .entry((period, project))
+            .or_insert((0, Duration::zero()));
+        total.0 += 1;
+        total.1 += duration;
+    }
+
+    totals
+        .into_iter()
+        .map(|((period, project), (sessions, tracked))| TimeTotal {
+            period,
+            project,
+            sessions,
+            tracked,
+        })
+        .collect()
+}
+
+/// Filters and orders a list of todos. All filters that are set must match
+/// for a todo to be kept.
+#[derive(Builder, Debug, Default)]
+pub struct TodoQuery {
+    #[builder(default)]
+    pub sort: Option<TodoSort>,
+    #[builder(default)]
+    pub reverse: bool,
+    #[builder(default)]
+    pub overdue: bool,
+    #[builder(default)]
+    pub due_within: Option<Duration>,
+    #[builder(default)]
+    pub no_deadline: bool,
+    #[builder(default)]
+    pub grep: Option<Regex>,
+}
+
+impl TodoQuery {
+    /// Filters and orders the todos a subtree at a time. Every match keeps
+    /// the todos it is nested under, and subtasks are only ordered among
+    /// their siblings, right after their parent.
+    pub fn apply(&self, todos: &mut Vec<ToDo>, today: NaiveDate) {
+        retain_nested(todos, |todo| self.matches(todo, today));
+
+        let TodoTree {
+            mut roots,
+            mut children,
+            ..
+        } = TodoTree::new(todos);
+        let order = |siblings: &mut Vec<usize>| {
+            if let Some(sort) = self.sort {
+                siblings.sort_by(|a, b| {
+                    compare(sort, &todos[*a], &todos[*b], self.reverse)
+                });
+            } else if self.reverse {
+                siblings.reverse();
+            }
+        };
+        order(&mut roots);
+        let mut ordered = Vec::with_capacity(todos.len());
+        let mut stack: Vec<usize> = roots.into_iter().rev().collect();
+        while let Some(at) = stack.pop() {
+            ordered.push(at);
+            if let Some(mut siblings) = children.remove(&at) {
+                order(&mut siblings);
+                stack.extend(siblings.into_iter().rev());
+            }
+        }
+
+        let mut slots: Vec<Option<ToDo>> = todos.drain(..).map(Some).collect();
+        todos.extend(ordered.into_iter().filter_map(|at| slots[at].take()));
+    }
+
+    pub fn matches(&self, todo: &ToDo, today: NaiveDate) -> bool {
+        if self.overdue && todo.deadline.is_none_or(|date| date >= today) {
+            return false;
+        }
+
+        if let Some(within) = self.due_within {
+            let limit = today + within;
+            if !todo
+                .deadline
+                .is_some_and(|date| today <= date && date <= limit)
+            {
+                return false;
+            }
+        }
+
+        if self.no_deadline && todo.deadline.is_some() {
+            return false;
+        }
+
+        if let Some(re) = &self.grep {
+            if !re.is_match(&todo.todo) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Keeps only the entries carrying every requested `#tag` and `@context`.
+#[derive(Builder, Debug, Default)]
+pub struct TagQuery {
+    #[builder(default)]
+    pub tags: Vec<String>,
+    #[builder(default)]
+    pub contexts: Vec<String>,
+}
+
+impl TagQuery {
+    pub fn apply(&self, sched: &mut Schedule) {
+        retain_nested(&mut sched.tbd_todos, |todo| self.matches(&todo.tags));
+        sched.comments.retain(|comment| self.matches(&comment.tags));
+        sched.blockers.retain(|blocker| self.matches(&blocker.tags));
+        sched.ideas.retain(|idea| self.matches(&idea.tags));
+        sched.events.retain(|_, event| self.matches(&event.tags));
+        sched.sessions.retain(|session| self.matches(&session.tags));
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        let has = |sigil: char, wanted: &String| {
+            let wanted = wanted.trim_start_matches(sigil);
+            tags.iter()
+                .any(|tag| tag.strip_prefix(sigil) == Some(wanted))
+        };
+
+        self.tags.iter().all(|tag| has('#', tag))
+            && self.contexts.iter().all(|context| has('@', context))
+    }
+}
+
+/// Todos by their position in a list, as subtasks of their parent.
+struct TodoTree {
+    roots: Vec<usize>,
+    children: HashMap<usize, Vec<usize>>,
+    parents: Vec<Option<usize>>,
+}
+
+impl TodoTree {
+    fn new(todos: &[ToDo]) -> Self {
+        let index: HashMap<&str, usize> = todos
+            .iter()
+            .enumerate()
+            .map(|(at, todo)| (todo.id.as_str(), at))
+            .collect();
+        let parents: Vec<Option<usize>> = todos
+            .iter()
+            .map(|todo| {
+                todo.parent.as_deref().and_then(|id| index.get(id).copied())
+            })
+            .collect();
+
+        let mut roots = Vec::new();
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (at, parent) in parents.iter().enumerate() {
+            match parent {
+                Some(parent) => children.entry(*parent).or_default().push(at),
+                None => roots.push(at),
+            }
+        }
+        TodoTree {
+            roots,
+            children,
+            parents,
+        }
+    }
+}
+
+/// Keeps the todos that match along with the todos they are nested under,
+/// so that no subtask is left without its parent.
+pub fn retain_nested(todos: &mut Vec<ToDo>, matches: impl Fn(&ToDo) -> bool) {
+    let parents = TodoTree::new(todos).parents;
+    let mut keep = vec![false; todos.len()];
+    for (at, todo) in todos.iter().enumerate() {
+        if !matches(todo) {
+            continue;
+        }
+        let mut next = Some(at);
+        while let Some(at) = next.filter(|at| !keep[*at]) {
+            keep[at] = true;
+            next = parents[at];
+        }
+    }
+    let mut keep = keep.into_iter();
+    todos.retain(|_| keep.next().expect("One flag per todo"));
+}
+
+/// Orders two todos by `sort`. Reversing keeps the todos without a value
+/// last.
+fn compare(sort: TodoSort, a: &ToDo, b: &ToDo, reverse: bool) -> Ordering {
+    match sort {
+        TodoSort::Date => {
+            let ordering =
+                (a.date, a.time_of_write).cmp(&(b.date, b.time_of_write));
+            if reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        }
+        TodoSort::Deadline => none_last(a.deadline, b.deadline, reverse),
+        TodoSort::TimeOfWrite => {
+            none_last(a.time_of_write, b.time_of_write, reverse)
+        }
+        TodoSort::Priority => none_last(a.priority, b.priority, reverse),
+    }
+}
+
+fn none_last<T: Ord>(a: Option<T>, b: Option<T>, reverse: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if reverse => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::structs::{self, Priority, TimeInterval};
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::Tz;
+
+    fn todo(
+        day: u32,
+        time: Option<u32>,
+        text: &str,
+        deadline: Option<u32>,
+    ) -> ToDo {
+        let tags = structs::find_tags(text, &structs::init_regexes().tags);
+        ToDo {
+            date: NaiveDate::from_ymd_opt(2024, 6, day).unwrap(),
+            time_of_write: time
+                .map(|hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap()),
+            todo: text.to_string(),
+            markdown: text.to_string(),
+            priority: None,
+            deadline: deadline
+                .map(|day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap()),
+            tags,
+            depth: 0,
+            progress: None,
+            parent: None,
+            line: 1,
+            id: String::new(),
+        }
+    }
+
+    fn sample() -> Vec<ToDo> {
+        vec![
+            todo(3, Some(9), "write report #work", Some(20)),
+            todo(1, Some(14), "call plumber @home", None),
+            todo(2, None, "review report #work @office", Some(10)),
+            todo(2, Some(8), "buy milk #errands @home", Some(16)),
+        ]
+    }
+
+    fn texts(todos: &[ToDo]) -> Vec<&str> {
+        todos.iter().map(|todo| todo.todo.as_str()).collect()
+    }
+
+    #[test]
+    fn check_sorting() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+
+        let mut todos = sample();
+        TodoQueryBuilder::default()
+            .sort(Some(TodoSort::Date))
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(
+            texts(&todos),
+            [
+                "call plumber @home",
+                "review report #work @office",
+                "buy milk #errands @home",
+                "write report #work"
+            ]
+        );
+
+        let mut todos = sample();
+        todos[0].priority = Some(Priority::Low);
+        todos[3].priority = Some(Priority::High);
+        TodoQueryBuilder::default()
+            .sort(Some(TodoSort::Priority))
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(
+            texts(&todos),
+            [
+                "buy milk #errands @home",
+                "write report #work",
+                "call plumber @home",
+                "review report #work @office"
+            ]
+        );
+
+        let mut todos = sample();
+        TodoQueryBuilder::default()
+            .sort(Some(TodoSort::Deadline))
+            .reverse(true)
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(
+            texts(&todos),
+            [
+                "write report #work",
+                "buy milk #errands @home",
+                "review report #work @office",
+                "call plumber @home"
+            ]
+        );
+    }
+
+    #[test]
+    fn check_nested_sorting() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let nested = || {
+            let mut todos = vec![
+                todo(1, None, "plan the move", Some(30)),
+                todo(1, None, "book the van", Some(20)),
+                todo(1, None, "pack the books", Some(25)),
+                todo(1, None, "call plumber", Some(10)),
+                todo(2, None, "fix the tap", None),
+            ];
+            for (at, todo) in todos.iter_mut().enumerate() {
+                todo.id = at.to_string();
+            }
+            for (child, parent) in [(1, 0), (2, 0), (4, 3)] {
+                todos[child].depth = 1;
+                todos[child].parent = Some(parent.to_string());
+            }
+            todos
+        };
+
+        let mut todos = nested();
+        TodoQueryBuilder::default()
+            .sort(Some(TodoSort::Deadline))
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(
+            texts(&todos),
+            [
+                "call plumber",
+                "fix the tap",
+                "plan the move",
+                "book the van",
+                "pack the books"
+            ]
+        );
+
+        let mut todos = nested();
+        TodoQueryBuilder::default()
+            .sort(Some(TodoSort::Deadline))
+            .reverse(true)
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(
+            texts(&todos),
+            [
+                "plan the move",
+                "pack the books",
+                "book the van",
+                "call plumber",
+                "fix the tap"
+            ]
+        );
+
+        let mut todos = nested();
+        TodoQueryBuilder::default()
+            .no_deadline(true)
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(texts(&todos), ["call plumber", "fix the tap"]);
+    }
+
+    #[test]
+    fn check_filters() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+
+        let mut todos = sample();
+        TodoQueryBuilder::default()
+            .overdue(true)
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(texts(&todos), ["review report #work @office"]);
+
+        let mut todos = sample();
+        TodoQueryBuilder::default()
+            .due_within(Some(Duration::days(3)))
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(texts(&todos), ["buy milk #errands @home"]);
+
+        let mut todos = sample();
+        TodoQueryBuilder::default()
+            .no_deadline(true)
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(texts(&todos), ["call plumber @home"]);
+
+        let mut todos = sample();
+        TodoQueryBuilder::default()
+            .grep(Some(Regex::new("report").unwrap()))
+            .build()
+            .unwrap()
+            .apply(&mut todos, today);
+        assert_eq!(
+            texts(&todos),
+            ["write report #work", "review report #work @office"]
+        );
+    }
+
+    #[test]
+    fn check_tag_filters() {
+        let query = TagQueryBuilder::default()
+            .tags(vec!["work".to_string()])
+            .contexts(vec!["@office".to_string()])
+            .build()
+            .unwrap();
+        let kept: Vec<_> = sample()
+            .into_iter()
+            .filter(|todo| query.matches(&todo.tags))
+            .collect();
+        assert_eq!(texts(&kept), ["review report #work @office"]);
+
+        let query = TagQueryBuilder::default()
+            .contexts(vec!["home".to_string()])
+            .build()
+            .unwrap();
+        let kept: Vec<_> = sample()
+            .into_iter()
+            .filter(|todo| query.matches(&todo.tags))
+            .collect();
+        assert_eq!(
+            texts(&kept),
+            ["call plumber @home", "buy milk #errands @home"]
+        );
+    }
+
+    fn event(day: u32, start: u32, hours: Option<i64>) -> CalEvent {
+        let date = NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+        let start = Tz::UTC
+            .from_local_datetime(&date.and_hms_opt(start, 0, 0).unwrap())
+            .unwrap();
+        let end = hours.map(|hours| start + Duration::hours(hours));
+        CalEvent {
+            start_time: TimeInterval((date, Some(start))),
+            end_time: TimeInterval((date, end)),
+            tags: Vec::new(),
+            description: String::new(),
+            location: None,
+            with: Vec::new(),
+            line: 1,
+            id: String::new(),
+        }
+    }
+
+    #[test]
+    fn check_schedule_totals() {
+        let events = HashMap::from([
+            ("standup".to_string(), event(10, 9, Some(1))),
+            ("review".to_string(), event(10, 14, Some(2))),
+            ("planning".to_string(), event(12, 10, Some(3))),
+            ("unclosed".to_string(), event(12, 16, None)),
+            ("retro".to_string(), event(17, 11, Some(1))),
+        ]);
+
+        assert_eq!(
+            schedule_totals(&events, TotalPeriod::Day),
+            [
+                ScheduleTotal {
+                    period: "2024-06-10".to_string(),
+                    events: 2,
+                    scheduled: Duration::hours(3),
+                },
+                ScheduleTotal {
+                    period: "2024-06-12".to_string(),
+                    events: 1,
+                    scheduled: Duration::hours(3),
+                },
+                ScheduleTotal {
+                    period: "2024-06-17".to_string(),
+                    events: 1,
+                    scheduled: Duration::hours(1),
+                },
+            ]
+        );
+        assert_eq!(
+            schedule_totals(&events, TotalPeriod::Week),
+            [
+                ScheduleTotal {
+                    period: "Week of 2024-06-10".to_string(),
+                    events: 3,
+                    scheduled: Duration::hours(6),
+                },
+                ScheduleTotal {
+                    period: "Week of 2024-06-17".to_string(),
+                    events: 1,
+                    scheduled: Duration::hours(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_schedule_conflicts() {
+        let events = HashMap::from([
+            ("standup".to_string(), event(10, 9, Some(1))),
+            ("interview".to_string(), event(10, 9, Some(2))),
+            ("lunch".to_string(), event(10, 10, Some(1))),
+            ("review".to_string(), event(10, 12, Some(-1))),
+            ("planning".to_string(), event(12, 10, None)),
+            ("retro".to_string(), event(12, 11, Some(1))),
+            ("reminder".to_string(), event(12, 11, None)),
+            ("call".to_string(), event(12, 12, None)),
+        ]);
+
+        let conflict = |day, kind, events: &[&str]| ScheduleConflict {
+            date: NaiveDate::from_ymd_opt(2024, 6, day).unwrap(),
+            kind,
+            events: events.iter().map(|event| event.to_string()).collect(),
+        };
+        let later_ends = [(
+            NaiveDate::from_ymd_opt(2024, 6, 14).unwrap(),
+            "planning".to_string(),
+        )];
+        assert_eq!(
+            schedule_conflicts(&events, &later_ends),
+            [
+                conflict(10, ConflictKind::Overlap, &["interview", "lunch"]),
+                conflict(10, ConflictKind::Overlap, &["interview", "standup"]),
+                conflict(10, ConflictKind::EndsBeforeStart, &["review"]),
+                conflict(12, ConflictKind::Overlap, &["retro", "reminder"]),
+                conflict(12, ConflictKind::Unclosed, &["planning"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_free_slots() {
+        let events = HashMap::from([
+            ("standup".to_string(), event(10, 9, Some(1))),
+            ("pairing".to_string(), event(10, 9, Some(2))),
+            ("lunch".to_string(), event(10, 12, Some(1))),
+            ("call".to_string(), event(10, 15, None)),
+            ("late".to_string(), event(10, 16, Some(3))),
+        ]);
+        let hours = WorkingHoursConfig::default();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        let at = |hour| {
+            Tz::UTC
+                .from_local_datetime(&monday.and_hms_opt(hour, 0, 0).unwrap())
+                .unwrap()
+        };
+        let slot = |start, end| FreeSlot {
+            date: monday,
+            start: at(start),
+            end: at(end),
+            length: at(end) - at(start),
+        };
+
+        assert_eq!(
+            free_slots(
+                &events,
+                monday,
+                &hours,
+                Duration::minutes(45),
+                Duration::hours(1),
+                &Tz::UTC,
+                false
+            ),
+            [slot(11, 12), slot(13, 15)]
+        );
+        assert_eq!(
+            free_slots(
+                &events,
+                monday,
+                &hours,
+                Duration::hours(2),
+                Duration::hours(1),
+                &Tz::UTC,
+                false
+            ),
+            [slot(13, 15)]
+        );
+
+        let saturday = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        assert!(free_slots(
+            &events,
+            saturday,
+            &hours,
+            Duration::minutes(45),
+            Duration::hours(1),
+            &Tz::UTC,
+            false
+        )
+        .is_empty());
+        assert_eq!(
+            free_slots(
+                &events,
+                saturday,
+                &hours,
+                Duration::minutes(45),
+                Duration::hours(1),
+                &Tz::UTC,
+                true
+            )
+            .len(),
+            1
+        );
+    }
+
+    fn session(day: u32, project: &str, minutes: i64) -> WorkSession {
+        let date = NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+        let start = Tz::UTC
+            .from_local_datetime(&date.and_hms_opt(9, 0, 0).unwrap())
+            .unwrap();
+        WorkSession {
+            date,
+            project: Some(project.to_string()),
+            start,
+            end: Some(start + Duration::minutes(minutes)),
+            tags: vec![format!("#{project}")],
+        }
+    }
+
+    #[test]
+    fn check_time_totals() {
+        let sessions = [
+            session(10, "calcu-rs", 90),
+            session(10, "docs", 30),
+            session(12, "calcu-rs", 60),
+            session(17, "calcu-rs", 45),
+        ];
+
+        let total =
+            |period: &str, project: &str, sessions, minutes| TimeTotal {
+                period: period.to_string(),
+                project: project.to_string(),
+                sessions,
+                tracked: Duration::minutes(minutes),
+            };
+
+        assert_eq!(
+            time_totals(&sessions, TimeGroup::Project, false),
+            [
+                total("Total", "calcu-rs", 3, 195),
+                total("Total", "docs", 1, 30)
+            ]
+        );
+        assert_eq!(
+            time_totals(&sessions, TimeGroup::Project, true),
+            [
+                total("Week of 2024-06-10", "calcu-rs", 2, 150),
+                total("Week of 2024-06-10", "docs", 1, 30),
+                total("Week of 2024-06-17", "calcu-rs", 1, 45)
+            ]
+        );
+        assert_eq!(
+            time_totals(&sessions, TimeGroup::Day, false),
+            [
+                total("2024-06-10", "All", 2, 120),
+                total("2024-06-12", "All", 1, 60),
+                total("2024-06-17", "All", 1, 45)
+            ]
+        );
+    }
+}
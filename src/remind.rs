@@ -0,0 +1,433 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::SystemTime,
+};
+
+use crate::config::{NotifierKind, ReminderConfig, UpperConfig};
+use crate::parse::{parse_duration, parse_sequence};
+use crate::query::TagQuery;
+use crate::structs::{localise, Schedule, DATE_FMT, TIME_FMT};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Days, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use log::{info, warn};
+
+/// A reminder of an event starting or a todo being due.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    /// Id of the event or the todo
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub due: DateTime<Tz>,
+    pub fire_at: DateTime<Tz>,
+}
+
+/// Every reminder of the events and the open todos with a deadline, one per
+/// lead time, in the order they fire. Deadlines and all day events are due
+/// at `due_at` on their day.
+pub fn reminders(
+    sched: &Schedule,
+    lead_times: &[Duration],
+    due_at: NaiveTime,
+    timezone: &Tz,
+) -> Vec<Reminder> {
+    let mut due = Vec::new();
+    for (name, event) in &sched.events {
+        let (start, body) = match event.start_time.0 .1 {
+            Some(start) => {
+                (start, format!("Starts at {}", start.format(TIME_FMT.fmt)))
+            }
+            None => (
+                localise(timezone, event.start_time.0 .0.and_time(due_at)),
+                "All day".to_string(),
+            ),
+        };
+        due.push((event.id.clone(), name.clone(), body, start));
+    }
+    for todo in &sched.tbd_todos {
+        if let Some(deadline) = todo.deadline {
+            due.push((
+                todo.id.clone(),
+                todo.todo.clone(),
+                format!("Due {}", deadline.format(DATE_FMT.fmt)),
+                localise(timezone, deadline.and_time(due_at)),
+            ));
+        }
+    }
+
+    let mut reminders: Vec<Reminder> = due
+        .into_iter()
+        .flat_map(|(id, title, body, due)| {
+            lead_times.iter().map(move |lead| Reminder {
+                id: id.clone(),
+                title: title.clone(),
+                body: body.clone(),
+                due,
+                fire_at: due - *lead,
+            })
+        })
+        .collect();
+    reminders.sort_by(|a, b| (a.fire_at, &a.id).cmp(&(b.fire_at, &b.id)));
+    reminders
+}
+
+/// Tells the time, so that tests can move it along.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+    fn sleep(&mut self, duration: Duration);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration.to_std().unwrap_or_default());
+    }
+}
+
+/// Somewhere reminders go.
+pub trait Notifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<()>;
+}
+
+impl Notifier for Box<dyn Notifier> {
+    fn notify(&mut self, reminder: &Reminder) -> Result<()> {
+        self.as_mut().notify(reminder)
+    }
+}
+
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<()> {
+        println!("{}: {} ({})", reminder.title, reminder.body, reminder.id);
+        Ok(())
+    }
+}
+
+/// Runs a command with the title and the body of the reminder as its last
+/// two arguments.
+pub struct CommandNotifier {
+    pub command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<()> {
+        let mut words = self.command.split_whitespace();
+        let Some(program) = words.next() else {
+            bail!("The reminder command is empty");
+        };
+        let status = Command::new(program)
+            .args(words)
+            .arg(&reminder.title)
+            .arg(&reminder.body)
+            .status()
+            .with_context(|| format!("Failed to run {}", self.command))?;
+        if !status.success() {
+            bail!("{} exited with {status}", self.command);
+        }
+        Ok(())
+    }
+}
+
+/// Shows desktop notifications through the freedesktop notification service
+/// on the session bus, by way of `gdbus`.
+pub struct DBusNotifier;
+
+impl Notifier for DBusNotifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<()> {
+        let status = Command::new("gdbus")
+            .args([
+                "call",
+                "--session",
+                "--dest=org.freedesktop.Notifications",
+                "--object-path=/org/freedesktop/Notifications",
+                "--method=org.freedesktop.Notifications.Notify",
+                "crs",
+                "0",
+                "",
+            ])
+            .arg(&reminder.title)
+            .arg(&reminder.body)
+            .args(["[]", "{}", "-1"])
+            .stdout(std::process::Stdio::null())
+            .status()
+            .context("Failed to run gdbus")?;
+        if !status.success() {
+            bail!("gdbus exited with {status}");
+        }
+        Ok(())
+    }
+}
+
+/// Builds the notifier the config asks for.
+pub fn notifier(config: &ReminderConfig) -> Result<Box<dyn Notifier>> {
+    Ok(match config.notifier {
+        NotifierKind::Stdout => Box::new(StdoutNotifier),
+        NotifierKind::Command => {
+            let Some(command) = config.command.clone() else {
+                bail!("The Command notifier needs a command in the config");
+            };
+            Box::new(CommandNotifier { command })
+        }
+        NotifierKind::DBus => Box::new(DBusNotifier),
+    })
+}
+
+/// Watches the notes and sends every reminder once, when its time comes and
+/// before what it reminds of is due.
+pub struct Reminders<'a, C: Clock, N: Notifier> {
+    config: &'a UpperConfig,
+    notes: PathBuf,
+    pub clock: C,
+    pub notifier: N,
+    lead_times: Vec<Duration>,
+    /// The `--tag` and `--context` filters
+    filter: TagQuery,
+    /// Day and state of the notes the reminders were worked out for
+    stamp: Option<(NaiveDate, usize, Option<SystemTime>)>,
+    reminders: Vec<Reminder>,
+    fired: HashSet<(String, DateTime<Tz>)>,
+}
+
+impl<'a, C: Clock, N: Notifier> Reminders<'a, C, N> {
+    pub fn new(
+        config: &'a UpperConfig,
+        notes: PathBuf,
+        clock: C,
+        notifier: N,
+        filter: TagQuery,
+    ) -> Result<Self> {
+        let lead_times = config
+            .reminders
+            .lead_times
+            .iter()
+            .map(|lead| {
+                parse_duration(lead)
+                    .with_context(|| format!("Invalid lead time {lead}"))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Reminders {
+            config,
+            notes,
+            clock,
+            notifier,
+            lead_times,
+            filter,
+            stamp: None,
+            reminders: Vec::new(),
+            fired: HashSet::new(),
+        })
+    }
+
+    /// Reads the notes again if they changed, and sends the reminders that
+    /// are due. Returns how many were sent.
+    pub fn tick(&mut self) -> Result<usize> {
+        let timezone = self.config.timezone();
+        let now = self.clock.now().with_timezone(&timezone);
+        let today = now.date_naive();
+
+        let (count, modified) = notes_stamp(&self.notes)?;
+        let stamp = Some((today, count, modified));
+        if stamp != self.stamp {
+            info!("Reading the notes for reminders");
+            let longest = self.lead_times.iter().max().copied();
+            let end = (now + longest.unwrap_or_default())
+                .date_naive()
+                .checked_add_days(Days::new(2))
+                .expect("Not the end of time");
+            // Events are keyed by name, so the days to come are read one by
+            // one to keep the daily ones of every day.
+            let mut ranges = vec![(self.config.start_date.min(today), today)];
+            ranges.extend(
+                today
+                    .iter_days()
+                    .take_while(|date| *date < end)
+                    .map(|date| (date, date + Days::new(1))),
+            );
+            self.reminders.clear();
+            for (start, end) in ranges {
+                let mut sched = parse_sequence(
+                    &start,
+                    &end,
+                    &mut self.notes.clone(),
+                    &self.config.callouts,
+                    &timezone,
+                );
+                self.filter.apply(&mut sched);
+                self.reminders.extend(reminders(
+                    &sched,
+                    &self.lead_times,
+                    self.config.reminders.due_at,
+                    &timezone,
+                ));
+            }
+            self.reminders
+                .sort_by(|a, b| (a.fire_at, &a.id).cmp(&(b.fire_at, &b.id)));
+            self.stamp = stamp;
+        }
+
+        let mut sent = 0;
+        for reminder in &self.reminders {
+            if reminder.fire_at > now || reminder.due <= now {
+                continue;
+            }
+            if !self.fired.insert((reminder.id.clone(), reminder.fire_at)) {
+                continue;
+            }
+            match self.notifier.notify(reminder) {
+                Ok(()) => sent += 1,
+                Err(e) => warn!("Failed to send a reminder: {e:?}"),
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Sends reminders until stopped. A failed look at the notes is logged
+    /// and tried again after the interval.
+    pub fn run(&mut self) -> Result<()> {
+        let interval = Duration::seconds(self.config.reminders.interval as i64);
+        loop {
+            if let Err(e) = self.tick() {
+                warn!("Failed to look for reminders: {e:?}");
+            }
+            self.clock.sleep(interval);
+        }
+    }
+}
+
+/// Number of files in the notes folder and when the last one changed.
+fn notes_stamp(notes: &Path) -> Result<(usize, Option<SystemTime>)> {
+    let mut count = 0;
+    let mut modified = None;
+    for entry in fs::read_dir(notes)
+        .with_context(|| format!("Failed to read {}", notes.display()))?
+    {
+        // Editors make and remove temporary files while we look.
+        let Ok(time) = entry
+            .and_then(|entry| entry.metadata())
+            .and_then(|metadata| metadata.modified())
+        else {
+            continue;
+        };
+        count += 1;
+        modified = modified.max(Some(time));
+    }
+    Ok((count, modified))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{
+        CommentConfig, ReminderConfigBuilder, ScheduleConfig, TodoConfig,
+        UpperConfigBuilder,
+    };
+    use chrono::TimeZone;
+
+    struct FakeClock(DateTime<Utc>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.0 += duration;
+        }
+    }
+
+    #[derive(Default)]
+    struct StubNotifier(Vec<String>);
+
+    impl Notifier for StubNotifier {
+        fn notify(&mut self, reminder: &Reminder) -> Result<()> {
+            self.0
+                .push(format!("{}: {}", reminder.title, reminder.body));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn check_reminders() {
+        let config = UpperConfigBuilder::default()
+            .start_date(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+            .timezone(Some(Tz::UTC))
+            .reminders(
+                ReminderConfigBuilder::default()
+                    .lead_times(vec!["10m".to_string(), "1h".to_string()])
+                    .build()
+                    .unwrap(),
+            )
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap();
+        let notes = tempfile::tempdir().unwrap();
+        let notes = notes.path();
+        let path = notes.join("2025-01-09.md");
+        fs::write(
+            &path,
+            "- [ ] Send the report DEADLINE: 2025-01-09\n- [x] Done DEADLINE: 2025-01-09\n\n\
+             > [!IMPORTANT]\n> Standup AT: 10:00 AM\n",
+        )
+        .unwrap();
+        fs::write(
+            notes.join("2025-01-10.md"),
+            "> [!IMPORTANT]\n> Standup AT: 10:00 AM\n",
+        )
+        .unwrap();
+
+        let clock =
+            FakeClock(Utc.with_ymd_and_hms(2025, 1, 9, 8, 0, 0).unwrap());
+        let mut reminders = Reminders::new(
+            &config,
+            notes.to_path_buf(),
+            clock,
+            StubNotifier::default(),
+            TagQuery::default(),
+        )
+        .unwrap();
+        let mut at = |minutes| {
+            reminders.clock.sleep(Duration::minutes(minutes));
+            reminders.tick().unwrap()
+        };
+
+        assert_eq!(at(0), 1);
+        assert_eq!(at(0), 0);
+        assert_eq!(at(49), 0);
+        assert_eq!(at(1), 1);
+        assert_eq!(at(10), 1);
+        fs::write(
+            &path,
+            "- [ ] Send the report DEADLINE: 2025-01-09\n\n\
+             > [!IMPORTANT]\n> Standup AT: 09:30 AM\n",
+        )
+        .unwrap();
+        assert_eq!(at(5), 1);
+        assert_eq!(at(15), 1);
+        assert_eq!(at(60), 0);
+
+        assert_eq!(
+            reminders.notifier.0,
+            [
+                "Send the report: Due 2025-01-09",
+                "Send the report: Due 2025-01-09",
+                "Standup: Starts at 10:00 AM",
+                "Standup: Starts at 09:30 AM",
+                "Standup: Starts at 09:30 AM",
+            ]
+        );
+    }
+}
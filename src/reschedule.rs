@@ -0,0 +1,534 @@
+use std::{
+    cmp::Reverse, fmt::Display, fs, ops::Range, path::Path, str::FromStr,
+};
+
+use crate::config::UpperConfig;
+use crate::edit::set_deadline;
+use crate::parse::{day_filename, ends_after, parse_sequence};
+use crate::structs::{
+    display_duration, init_regexes, AllRegexes, CalEvent, Schedule, ToDo,
+    TIME_FMT,
+};
+use crate::template::create_day;
+
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDate, NaiveTime};
+use regex::Regex;
+
+/// A todo or an event, addressed by its id, like `250621-3fa9`, or by the
+/// day it was written on and the line it starts on, like `2024-06-21:12`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemRef {
+    Id(String),
+    Line { date: NaiveDate, line: usize },
+}
+
+impl ItemRef {
+    /// Day of the note the item is in.
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            ItemRef::Id(id) => NaiveDate::parse_from_str(&id[..6], "%y%m%d")
+                .expect("Checked when parsed"),
+            ItemRef::Line { date, .. } => *date,
+        }
+    }
+
+    fn matches(&self, id: &str, date: NaiveDate, line: usize) -> bool {
+        match self {
+            ItemRef::Id(item) => item == id,
+            ItemRef::Line {
+                date: item_date,
+                line: item_line,
+            } => *item_date == date && *item_line == line,
+        }
+    }
+}
+
+impl Display for ItemRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemRef::Id(id) => write!(f, "{id}"),
+            ItemRef::Line { date, line } => write!(f, "{date}:{line}"),
+        }
+    }
+}
+
+impl FromStr for ItemRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = Regex::new(r"^[0-9]{6}-[0-9a-f]{4}$").unwrap();
+        if id.is_match(s)
+            && NaiveDate::parse_from_str(&s[..6], "%y%m%d").is_ok()
+        {
+            return Ok(ItemRef::Id(s.to_string()));
+        }
+
+        let invalid = || {
+            format!("{s} is not an id like 250621-3fa9 or a line like 2024-06-21:12")
+        };
+        let (date, line) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(ItemRef::Line {
+            date: date.parse().map_err(|_| invalid())?,
+            line: line.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Something in the notes that can be rescheduled.
+#[derive(Debug)]
+pub enum Item<'a> {
+    Todo(&'a ToDo),
+    Event(&'a str, &'a CalEvent),
+}
+
+impl Item<'_> {
+    pub fn name(&self) -> &str {
+        match self {
+            Item::Todo(todo) => &todo.todo,
+            Item::Event(name, _) => name,
+        }
+    }
+}
+
+/// Looks up the todo or the event the reference points at.
+pub fn find_item<'a>(sched: &'a Schedule, item: &ItemRef) -> Option<Item<'a>> {
+    let todo = sched
+        .tbd_todos
+        .iter()
+        .find(|todo| item.matches(&todo.id, todo.date, todo.line))
+        .map(Item::Todo);
+    todo.or_else(|| {
+        sched
+            .events
+            .iter()
+            .find(|(_, event)| {
+                item.matches(&event.id, event.start_time.0 .0, event.line)
+            })
+            .map(|(name, event)| Item::Event(name, event))
+    })
+}
+
+/// Moves the deadline of a todo, or an event to another day and optionally
+/// another time. An event that moves to another day is taken out of its note
+/// and added to the one of the day. Returns the name of the item.
+pub fn reschedule(
+    notes: &Path,
+    item: &ItemRef,
+    to: NaiveDate,
+    at: Option<NaiveTime>,
+    config: &UpperConfig,
+) -> Result<String> {
+    let date = item.date();
+    let sched = parse_sequence(
+        &date,
+        &date.succ_opt().expect("Not the end of time"),
+        &mut notes.to_path_buf(),
+        &config.callouts,
+        &config.timezone(),
+    );
+    let Some(found) = find_item(&sched, item) else {
+        bail!("No open todo or event is {item}");
+    };
+
+    let all_regexes = init_regexes();
+    let path = notes.join(day_filename(&date));
+    match found {
+        Item::Todo(_) if at.is_some() => bail!("Todos have no time to move"),
+        Item::Todo(todo) => set_deadline(&path, todo.line, to, &all_regexes)?,
+        Item::Event(name, event) => {
+            move_event(notes, name, event, to, at, config, &all_regexes)?
+        }
+    }
+    Ok(found.name().to_string())
+}
+
+fn move_event(
+    notes: &Path,
+    name: &str,
+    event: &CalEvent,
+    to: NaiveDate,
+    at: Option<NaiveTime>,
+    config: &UpperConfig,
+    all_regexes: &AllRegexes,
+) -> Result<()> {
+    let from = event.start_time.0 .0;
+    // An END block on a later day only turns up reading on to it.
+    let spanned;
+    let event = match event.end_time.0 .1 {
+        Some(_) => event,
+        None => {
+            let next = from.succ_opt().expect("Not the end of time");
+            let end = ends_after(
+                &next,
+                &mut notes.to_path_buf(),
+                &config.callouts,
+                &config.timezone(),
+            )
+            .into_iter()
+            .find(|(_, end)| end == name);
+            match end {
+                Some((last, _)) => {
+                    spanned = parse_sequence(
+                        &from,
+                        &last.succ_opt().expect("Not the end of time"),
+                        &mut notes.to_path_buf(),
+                        &config.callouts,
+                        &config.timezone(),
+                    );
+                    spanned.events.get(name).unwrap_or(event)
+                }
+                None => event,
+            }
+        }
+    };
+
+    let path = notes.join(day_filename(&from));
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut lines: Vec<String> =
+        contents.split_inclusive('\n').map(String::from).collect();
+
+    let quote = Regex::new(r"^((?:\s*>)+\s*)(.*?)\s*$").unwrap();
+    let block = block_at(&lines, event.line - 1);
+    let Some(title) = block.clone().skip(1).find(|index| {
+        quote
+            .captures(&lines[*index])
+            .is_some_and(|cap| !cap[2].is_empty())
+    }) else {
+        bail!("The event {name} has no title");
+    };
+
+    // The event now says when it starts and how long it takes, so that a
+    // separate END block is no longer needed.
+    let timing = match at.or(event.start_time.0 .1.map(|time| time.time())) {
+        Some(time) => match event.duration() {
+            Some(duration) => format!(
+                "AT: {} FOR: {}",
+                time.format(TIME_FMT.fmt),
+                display_duration(&duration).replace(' ', "")
+            ),
+            None => format!("AT: {}", time.format(TIME_FMT.fmt)),
+        },
+        None => "ALL DAY".to_string(),
+    };
+    let timings = Regex::new(&format!(
+        r"\s*(?:{}|{}|{}|{})",
+        all_regexes.at_time.as_str(),
+        all_regexes.end.as_str(),
+        all_regexes.for_duration.as_str(),
+        all_regexes.all_day.as_str()
+    ))
+    .unwrap();
+
+    // The first timing becomes the new one and the others go, leaving the
+    // rest of the lines as they were written.
+    let mut moved = lines[block.start..title].to_vec();
+    let mut placed = false;
+    for (index, line) in lines.iter().enumerate().take(block.end).skip(title) {
+        let Some((prefix, length)) =
+            quote_text(&quote, line).filter(|(prefix, length)| {
+                timings.is_match(&line[*prefix..prefix + length])
+            })
+        else {
+            moved.push(line.clone());
+            continue;
+        };
+        let text = &line[prefix..prefix + length];
+        let mut rewritten = String::new();
+        let mut rest = text;
+        if !placed {
+            let first = timings.find(text).expect("Matched above");
+            rewritten.push_str(&text[..first.start()]);
+            if first.start() > 0 {
+                rewritten.push(' ');
+            }
+            rewritten.push_str(&timing);
+            rest = &text[first.end()..];
+            placed = true;
+        }
+        rewritten.push_str(&timings.replace_all(rest, ""));
+        let rewritten = rewritten.trim();
+        if rewritten.is_empty() && index != title {
+            continue;
+        }
+        moved.push(format!(
+            "{}{rewritten}{}",
+            &line[..prefix],
+            &line[prefix + length..].trim_start_matches([' ', '\t'])
+        ));
+    }
+    if !placed {
+        let line = &mut moved[title - block.start];
+        let (prefix, length) = quote_text(&quote, line).expect("Found above");
+        line.insert_str(prefix + length, &format!(" {timing}"));
+    }
+    let block = block.start..block.start + moved.len();
+    lines.splice(block.clone(), moved.clone());
+    if !moved.last().is_some_and(|line| line.ends_with('\n')) {
+        moved.last_mut().expect("Never empty").push('\n');
+    }
+
+    // Blocks are taken out from the bottom up to keep the lines above put.
+    let mut taken = end_blocks(&lines, name, all_regexes);
+    if to != from {
+        taken.push(block.clone());
+    }
+    taken.sort_by_key(|range| Reverse(range.start));
+    for range in taken {
+        remove_block(&mut lines, range);
+    }
+    fs::write(&path, lines.concat())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    for day in from
+        .iter_days()
+        .skip(1)
+        .take_while(|day| *day <= event.end_time.0 .0)
+    {
+        let path = notes.join(day_filename(&day));
+        if !path.exists() {
+            continue;
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut lines: Vec<String> =
+            contents.split_inclusive('\n').map(String::from).collect();
+        let mut taken = end_blocks(&lines, name, all_regexes);
+        if taken.is_empty() {
+            continue;
+        }
+        taken.sort_by_key(|range| Reverse(range.start));
+        for range in taken {
+            remove_block(&mut lines, range);
+        }
+        fs::write(&path, lines.concat())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    if to != from {
+        create_day(notes, to, config, &[])?;
+        let target = notes.join(day_filename(&to));
+        let mut contents = fs::read_to_string(&target)
+            .with_context(|| format!("Failed to read {}", target.display()))?;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        if contents
+            .lines()
+            .last()
+            .is_some_and(|line| !line.trim().is_empty())
+        {
+            contents.push('\n');
+        }
+        contents.push_str(&moved.concat());
+        fs::write(&target, contents)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    Ok(())
+}
+
+/// Where the text of a quoted line starts and how long it is, leaving out
+/// the `>` markers and the trailing whitespace.
+fn quote_text(quote: &Regex, line: &str) -> Option<(usize, usize)> {
+    quote.captures(line).map(|cap| (cap[1].len(), cap[2].len()))
+}
+
+/// Lines of the block quote starting at the 0-based index.
+fn block_at(lines: &[String], start: usize) -> Range<usize> {
+    let end = (start + 1..lines.len())
+        .find(|index| !lines[*index].trim_start().starts_with('>'))
+        .unwrap_or(lines.len());
+    start..end
+}
+
+/// Blocks ending the event with an `END:` time.
+fn end_blocks(
+    lines: &[String],
+    name: &str,
+    all_regexes: &AllRegexes,
+) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        if !all_regexes.callout.is_match(lines[index].trim_end()) {
+            index += 1;
+            continue;
+        }
+        let block = block_at(lines, index);
+        let ends = lines[block.clone()].iter().skip(1).any(|line| {
+            let text = line.trim_start().trim_start_matches(['>', ' ']);
+            all_regexes.end.is_match(text)
+                && all_regexes.end.replace(text, "").trim() == name
+        });
+        if ends {
+            blocks.push(block.clone());
+        }
+        index = block.end;
+    }
+    blocks
+}
+
+/// Removes the lines along with a blank line around them.
+fn remove_block(lines: &mut Vec<String>, block: Range<usize>) {
+    let start = block.start;
+    lines.drain(block);
+    let blank = |line: &String| line.trim().is_empty();
+    if start < lines.len()
+        && blank(&lines[start])
+        && (start == 0 || blank(&lines[start - 1]))
+    {
+        lines.remove(start);
+    } else if start == lines.len() && start > 0 && blank(&lines[start - 1]) {
+        lines.remove(start - 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{
+        CommentConfig, ScheduleConfig, TodoConfig, UpperConfigBuilder,
+    };
+    use chrono_tz::Tz;
+
+    #[test]
+    fn check_item_refs() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        assert_eq!(
+            "2024-06-21:12".parse(),
+            Ok(ItemRef::Line { date, line: 12 })
+        );
+        assert_eq!(
+            "240621-3fa9".parse(),
+            Ok(ItemRef::Id("240621-3fa9".to_string()))
+        );
+        assert!("241321-3fa9".parse::<ItemRef>().is_err());
+        assert!("2024-06-21".parse::<ItemRef>().is_err());
+        assert!("friday:3".parse::<ItemRef>().is_err());
+    }
+
+    #[test]
+    fn check_reschedule() {
+        let config = UpperConfigBuilder::default()
+            .start_date(NaiveDate::from_ymd_opt(2025, 1, 9).unwrap())
+            .timezone(Some(Tz::UTC))
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap();
+        let notes = tempfile::tempdir().unwrap();
+        let notes = notes.path();
+        let day = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let item = |date, line| ItemRef::Line {
+            date: day(date),
+            line,
+        };
+        fs::copy("tests/2025-01-09.md", notes.join("2025-01-09.md")).unwrap();
+        fs::write(
+            notes.join("2025-01-09.md"),
+            fs::read_to_string(notes.join("2025-01-09.md")).unwrap()
+                + "\n- [ ] Book the room DEADLINE: 2025-01-10\n",
+        )
+        .unwrap();
+
+        let half_three = NaiveTime::from_hms_opt(15, 30, 0);
+        assert!(reschedule(notes, &item(9, 4), day(10), None, &config).is_err());
+        assert!(
+            reschedule(notes, &item(9, 21), day(10), half_three, &config)
+                .is_err()
+        );
+        assert_eq!(
+            reschedule(notes, &item(9, 21), day(13), None, &config).unwrap(),
+            "Book the room"
+        );
+        assert_eq!(
+            reschedule(notes, &item(9, 3), day(10), half_three, &config)
+                .unwrap(),
+            "Design review"
+        );
+        let nine = NaiveTime::from_hms_opt(9, 0, 0);
+        let offsite = ItemRef::from_str("250109-4871").unwrap();
+        assert_eq!(offsite.date(), day(9));
+        assert_eq!(
+            reschedule(notes, &offsite, day(9), nine, &config).unwrap(),
+            "Offsite"
+        );
+
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-09.md")).unwrap(),
+            "# Event details\n\n\
+             > [!IMPORTANT]\n> Offsite AT: 09:00 AM\n> LOCATION: Lakeside\n\n\
+             > [!IMPORTANT]\n> Pairing session AT: 10:00 AM FOR: 1h30m\n\
+             > WITH: Ben\n\n\
+             - [ ] Book the room DEADLINE: 2025-01-13\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-10.md")).unwrap(),
+            "# Friday 2025-01-10\n\n\
+             > [!IMPORTANT]\n> Design review AT: 03:30 PM FOR: 1h\n\
+             > Walk through the new parser.\n> LOCATION: Room 4\n\
+             > WITH: Asha, Ben\n> Bring the benchmarks.\n"
+        );
+    }
+
+    #[test]
+    fn check_reschedule_in_place() {
+        let config = UpperConfigBuilder::default()
+            .start_date(NaiveDate::from_ymd_opt(2025, 1, 13).unwrap())
+            .timezone(Some(Tz::UTC))
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap();
+        let notes = tempfile::tempdir().unwrap();
+        let notes = notes.path();
+        let day = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        let item = |line| ItemRef::Line {
+            date: day(13),
+            line,
+        };
+        fs::write(
+            notes.join("2025-01-13.md"),
+            "# Monday\n\n\
+             > [!IMPORTANT]\n> **Launch** party with [the team](team.md)\n\
+             > AT: 06:00 PM FOR: 2h\n> LOCATION: Rooftop\n\n\
+             > [!IMPORTANT]\n> Hackathon AT: 09:00 AM\n",
+        )
+        .unwrap();
+        fs::write(
+            notes.join("2025-01-14.md"),
+            "# Tuesday\n\n> [!IMPORTANT]\n> Hackathon END: 05:00 PM\n\n\
+             - [ ] Demo\n",
+        )
+        .unwrap();
+
+        let seven = NaiveTime::from_hms_opt(19, 0, 0);
+        assert_eq!(
+            reschedule(notes, &item(3), day(13), seven, &config).unwrap(),
+            "Launch party with the team"
+        );
+        assert_eq!(
+            reschedule(notes, &item(8), day(20), None, &config).unwrap(),
+            "Hackathon"
+        );
+
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-13.md")).unwrap(),
+            "# Monday\n\n\
+             > [!IMPORTANT]\n> **Launch** party with [the team](team.md)\n\
+             > AT: 07:00 PM FOR: 2h\n> LOCATION: Rooftop\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-14.md")).unwrap(),
+            "# Tuesday\n\n- [ ] Demo\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-20.md")).unwrap(),
+            "# Monday 2025-01-20\n\n\
+             > [!IMPORTANT]\n> Hackathon AT: 09:00 AM FOR: 32h\n"
+        );
+    }
+}
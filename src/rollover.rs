@@ -0,0 +1,222 @@
+use std::{fs, path::Path};
+
+use crate::config::UpperConfig;
+use crate::edit::{append_tasks, migrate_todo, todo_line};
+use crate::parse::{day_filename, parse_sequence};
+use crate::structs::{ToDo, DATE_FMT};
+use crate::template::create_day;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+
+/// Carries the open todos of the days before into the note of `today` and
+/// marks the originals as migrated with `- [>]`. With `link` set, every
+/// carried todo links back to the day it came from. Todos already in today's
+/// note are only marked. A new note of `today` is made from the day
+/// template, which may place the carried todos itself. Returns the todos
+/// that were migrated.
+pub fn rollover(
+    notes: &Path,
+    today: NaiveDate,
+    config: &UpperConfig,
+    link: bool,
+) -> Result<Vec<ToDo>> {
+    let schedule = parse_sequence(
+        &config.start_date.min(today),
+        &today,
+        &mut notes.to_path_buf(),
+        &config.callouts,
+        &config.timezone(),
+    );
+    let open: Vec<ToDo> = schedule
+        .tbd_todos
+        .into_iter()
+        .filter(|todo| todo.date < today)
+        .collect();
+    if open.is_empty() {
+        return Ok(open);
+    }
+
+    let mut carried = Vec::new();
+    // Depth of the last carried todo of the same day, so that subtasks of a
+    // finished task don't end up under someone else's.
+    let mut last: Option<(NaiveDate, usize)> = None;
+    for todo in &open {
+        let depth = match last {
+            Some((date, depth)) if date == todo.date => {
+                todo.depth.min(depth + 1)
+            }
+            _ => 0,
+        };
+        last = Some((todo.date, depth));
+        carried.push(ToDo {
+            depth,
+            ..todo.clone()
+        });
+    }
+    let linked: Vec<ToDo> = carried
+        .iter()
+        .map(|todo| {
+            let mut todo = todo.clone();
+            if link {
+                let date = todo.date.format(DATE_FMT.fmt);
+                todo.markdown = format!(
+                    "{} (from [{date}]({}))",
+                    todo.markdown,
+                    day_filename(&todo.date)
+                );
+            }
+            todo
+        })
+        .collect();
+
+    create_day(notes, today, config, &linked.iter().collect::<Vec<_>>())?;
+    let path = notes.join(day_filename(&today));
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    // Skips the ones today's note has already, be it from before or from
+    // the template.
+    let lines: Vec<String> = carried
+        .iter()
+        .zip(&linked)
+        .filter(|(todo, linked)| {
+            !has_block(&contents, &todo_line(todo))
+                && !has_block(&contents, &todo_line(linked))
+        })
+        .map(|(_, linked)| todo_line(linked))
+        .collect();
+    if !lines.is_empty() {
+        append_tasks(&path, &lines)?;
+    }
+
+    // Left open, they would be carried again into every day after.
+    for todo in &open {
+        migrate_todo(&notes.join(day_filename(&todo.date)), todo.line)?;
+    }
+    Ok(open)
+}
+
+/// Whether the lines of `block` follow each other in `contents`, whatever
+/// their indentation.
+fn has_block(contents: &str, block: &str) -> bool {
+    let lines: Vec<&str> = contents.lines().map(str::trim).collect();
+    let block: Vec<&str> = block.lines().map(str::trim).collect();
+    lines.windows(block.len()).any(|window| window == block)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{
+        CommentConfig, ScheduleConfig, TemplateConfigBuilder, TodoConfig,
+        UpperConfigBuilder,
+    };
+    use chrono_tz::Tz;
+
+    #[test]
+    fn check_rollover() {
+        let config = UpperConfigBuilder::default()
+            .start_date(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+            .timezone(Some(Tz::UTC))
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap();
+        let notes = tempfile::tempdir().unwrap();
+        let notes = notes.path();
+        fs::write(
+            notes.join("2025-01-06.md"),
+            "# Monday\n\n- [x] Plan\n  - [ ] Book flights DEADLINE: 2025-01-20\n- [ ] Ship it #work\n",
+        )
+        .unwrap();
+        fs::write(notes.join("2025-01-07.md"), "- [ ] Write docs\n").unwrap();
+        fs::write(
+            notes.join("2025-01-08.md"),
+            "# Today\n\n- [ ] Ship it #work\n",
+        )
+        .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 8).unwrap();
+        let rolled = rollover(notes, today, &config, true).unwrap();
+        assert_eq!(rolled.len(), 3);
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-06.md")).unwrap(),
+            "# Monday\n\n- [x] Plan\n  - [>] Book flights DEADLINE: 2025-01-20\n- [>] Ship it #work\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-07.md")).unwrap(),
+            "- [>] Write docs\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-08.md")).unwrap(),
+            "# Today\n\n- [ ] Ship it #work\n\
+             - [ ] Book flights (from [2025-01-06](2025-01-06.md)) DEADLINE: 2025-01-20\n\
+             - [ ] Write docs (from [2025-01-07](2025-01-07.md))\n"
+        );
+        assert!(rollover(notes, today, &config, false).unwrap().is_empty());
+
+        // The template of a new day places the carried todos.
+        fs::write(notes.join("2025-01-08.md"), "- [ ] Ship it\n").unwrap();
+        fs::write(notes.join("template.md"), "# Day\n\n{{carried_todos}}\n")
+            .unwrap();
+        let config = UpperConfig {
+            templates: TemplateConfigBuilder::default()
+                .day(Some("template.md".into()))
+                .build()
+                .unwrap(),
+            ..config
+        };
+        let today = today.succ_opt().unwrap();
+        let rolled = rollover(notes, today, &config, true).unwrap();
+        assert_eq!(rolled.len(), 1);
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-08.md")).unwrap(),
+            "- [>] Ship it\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-09.md")).unwrap(),
+            "# Day\n\n- [ ] Ship it (from [2025-01-08](2025-01-08.md))\n"
+        );
+    }
+
+    #[test]
+    fn check_rollover_multiline() {
+        let config = UpperConfigBuilder::default()
+            .start_date(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+            .timezone(Some(Tz::UTC))
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap();
+        let notes = tempfile::tempdir().unwrap();
+        let notes = notes.path();
+        fs::write(
+            notes.join("2025-01-06.md"),
+            "- [ ] Write the release notes\n  for **1.0** #work\n\
+             - [ ] Book the venue\n  before Friday\n",
+        )
+        .unwrap();
+        fs::write(
+            notes.join("2025-01-07.md"),
+            "- [ ] Write the release notes\n    for **1.0** #work\n",
+        )
+        .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 7).unwrap();
+        let rolled = rollover(notes, today, &config, false).unwrap();
+        assert_eq!(rolled.len(), 2);
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-06.md")).unwrap(),
+            "- [>] Write the release notes\n  for **1.0** #work\n\
+             - [>] Book the venue\n  before Friday\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-07.md")).unwrap(),
+            "- [ ] Write the release notes\n    for **1.0** #work\n\
+             - [ ] Book the venue\n      before Friday\n"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::query::TagQuery;
+use crate::structs::{find_tags, Schedule};
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use tabled::Tabled;
+
+/// Kind of entry a search hit comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKind {
+    Todo,
+    Event,
+    Log,
+    Blocker,
+    Idea,
+    Prose,
+}
+
+impl Display for EntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryKind::Todo => write!(f, "Todo"),
+            EntryKind::Event => write!(f, "Event"),
+            EntryKind::Log => write!(f, "Log"),
+            EntryKind::Blocker => write!(f, "Blocker"),
+            EntryKind::Idea => write!(f, "Idea"),
+            EntryKind::Prose => write!(f, "Prose"),
+        }
+    }
+}
+
+impl FromStr for EntryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "todo" | "todos" => Ok(EntryKind::Todo),
+            "event" | "events" | "schedule" => Ok(EntryKind::Event),
+            "log" | "logs" => Ok(EntryKind::Log),
+            "blocker" | "blockers" => Ok(EntryKind::Blocker),
+            "idea" | "ideas" => Ok(EntryKind::Idea),
+            "prose" => Ok(EntryKind::Prose),
+            _ => Err(format!("Unknown entry type {s}")),
+        }
+    }
+}
+
+/// A search over the entries of a schedule. Every part that is set must match
+/// for an entry to be a hit.
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub text: Option<Regex>,
+    /// Kinds of entries to look at. All of them if empty, except for prose
+    pub kinds: Vec<EntryKind>,
+    pub tags: TagQuery,
+    /// Only entries of the days strictly before this one
+    pub before: Option<NaiveDate>,
+    /// Only entries of the days strictly after this one
+    pub after: Option<NaiveDate>,
+}
+
+impl SearchQuery {
+    /// Parses a query like `type:todo tag:work before:2024-06-01 report`.
+    /// The words that are not filters are searched for as one phrase, which
+    /// is a regex if `regex` is set and case insensitive text otherwise.
+    pub fn parse(query: &str, regex: bool) -> Result<Self> {
+        let mut search = SearchQuery::default();
+        let mut words = Vec::new();
+
+        for word in query.split_whitespace() {
+            let Some((field, value)) = word.split_once(':') else {
+                words.push(word);
+                continue;
+            };
+            match field {
+                "type" => search.kinds.push(value.parse().map_err(|e| {
+                    anyhow::anyhow!("Invalid filter {word}: {e}")
+                })?),
+                "tag" if value.starts_with('@') => {
+                    search.tags.contexts.push(value.to_string())
+                }
+                "tag" => search.tags.tags.push(value.to_string()),
+                "before" => {
+                    search.before = Some(value.parse().with_context(|| {
+                        format!("Invalid date in filter {word}")
+                    })?)
+                }
+                "after" => {
+                    search.after = Some(value.parse().with_context(|| {
+                        format!("Invalid date in filter {word}")
+                    })?)
+                }
+                _ => words.push(word),
+            }
+        }
+
+        if !words.is_empty() {
+            let phrase = words.join(" ");
+            let pattern = if regex {
+                phrase
+            } else {
+                regex::escape(&phrase)
+            };
+            search.text = Some(
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(!regex)
+                    .build()
+                    .with_context(|| format!("Invalid regex {pattern}"))?,
+            );
+        }
+        if search.text.is_none()
+            && search.kinds.is_empty()
+            && search.tags.tags.is_empty()
+            && search.tags.contexts.is_empty()
+            && search.before.is_none()
+            && search.after.is_none()
+        {
+            bail!("Empty search");
+        }
+
+        Ok(search)
+    }
+
+    fn matches(&self, date: NaiveDate, text: &str, tags: &[String]) -> bool {
+        self.before.is_none_or(|before| date < before)
+            && self.after.is_none_or(|after| date > after)
+            && self.tags.matches(tags)
+            && self.text.as_ref().is_none_or(|re| re.is_match(text))
+    }
+}
+
+#[derive(Debug, PartialEq, Tabled)]
+pub struct SearchHit {
+    #[tabled(rename = "Date")]
+    pub date: NaiveDate,
+    #[tabled(rename = "Line")]
+    pub line: usize,
+    #[tabled(rename = "Type")]
+    pub kind: EntryKind,
+    #[tabled(rename = "Match")]
+    pub text: String,
+}
+
+/// Searches the entries of the schedule, in the order they were written.
+/// Prose is only searched if `prose` is set or the query asks for it.
+pub fn search(
+    sched: &Schedule,
+    query: &SearchQuery,
+    prose: bool,
+    tag_search: &Regex,
+) -> Vec<SearchHit> {
+    let mut kinds = query.kinds.clone();
+    if kinds.is_empty() {
+        kinds = vec![
+            EntryKind::Todo,
+            EntryKind::Event,
+            EntryKind::Log,
+            EntryKind::Blocker,
+            EntryKind::Idea,
+        ];
+    }
+    if prose {
+        kinds.push(EntryKind::Prose);
+    }
+
+    let mut hits = Vec::new();
+    let mut hit = |kind, date, line, text: &str, tags: &[String]| {
+        if kinds.contains(&kind) && query.matches(date, text, tags) {
+            hits.push(SearchHit {
+                date,
+                line,
+                kind,
+                text: text.trim().to_string(),
+            });
+        }
+    };
+
+    for todo in &sched.tbd_todos {
+        hit(
+            EntryKind::Todo,
+            todo.date,
+            todo.line,
+            &todo.todo,
+            &todo.tags,
+        );
+    }
+    for (name, event) in &sched.events {
+        let text = [name.as_str(), &event.description].join("\n");
+        hit(
+            EntryKind::Event,
+            event.start_time.0 .0,
+            event.line,
+            text.trim(),
+            &event.tags,
+        );
+    }
+    for (kind, comments) in [
+        (EntryKind::Log, &sched.comments),
+        (EntryKind::Blocker, &sched.blockers),
+        (EntryKind::Idea, &sched.ideas),
+    ] {
+        for comment in comments {
+            hit(
+                kind,
+                comment.date,
+                comment.line,
+                &comment.comment,
+                &comment.tags,
+            );
+        }
+    }
+    for prose in &sched.prose {
+        let tags = find_tags(&prose.text, tag_search);
+        hit(EntryKind::Prose, prose.date, prose.line, &prose.text, &tags);
+    }
+
+    hits.sort_by_key(|hit| (hit.date, hit.line, hit.kind));
+    hits
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::CalloutConfig;
+    use crate::parse::parse_sequence;
+    use crate::structs::init_regexes;
+    use chrono_tz::Tz;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    fn found(query: &str, regex: bool, prose: bool) -> Vec<(u32, usize)> {
+        use chrono::Datelike;
+
+        let sched = parse_sequence(
+            &NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 1, 12).unwrap(),
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
+        );
+        search(
+            &sched,
+            &SearchQuery::parse(query, regex).unwrap(),
+            prose,
+            &init_regexes().tags,
+        )
+        .iter()
+        .map(|hit| (hit.date.day(), hit.line))
+        .collect()
+    }
+
+    #[test]
+    fn check_search() {
+        assert_eq!(found("tag:work", false, false), [(6, 3), (6, 10), (6, 13)]);
+        assert_eq!(found("type:todo tag:@office", false, false), [(6, 3)]);
+        assert_eq!(found("BOOK", false, false), [(6, 15), (7, 5)]);
+        assert_eq!(found("book", false, true), [(6, 15), (7, 5)]);
+        assert_eq!(
+            found(r"^(Water|Cache) the", true, false),
+            [(6, 5), (10, 6)]
+        );
+        assert_eq!(
+            found("type:prose after:2025-01-09 before:2025-01-11", false, true),
+            [(10, 1)]
+        );
+        assert_eq!(found("type:event review", false, false), [(9, 3)]);
+        assert_eq!(found("type:log lisbon", false, false), [(11, 15)]);
+    }
+
+    #[test]
+    fn check_search_query() {
+        assert!(SearchQuery::parse("", false).is_err());
+        assert!(SearchQuery::parse("type:nonsense", false).is_err());
+        assert!(SearchQuery::parse("before:yesterday", false).is_err());
+        assert!(SearchQuery::parse("(", true).is_err());
+
+        let query = SearchQuery::parse("a.b", false).unwrap();
+        assert!(query.text.as_ref().unwrap().is_match("A.B"));
+        assert!(!query.text.as_ref().unwrap().is_match("axb"));
+    }
+}
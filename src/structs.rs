@@ -1,25 +1,43 @@
-use std::{collections::HashMap, fmt::Display, ops::Deref};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    ops::Deref,
+    str::FromStr,
+};
 
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{
+    DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+};
+use chrono_tz::Tz;
 use regex::Regex;
+use serde::{Deserialize, Deserializer};
 use tabled::Tabled;
 
+/// A day and, unless the entry spans the whole day, the time in the zone the
+/// note was written in.
 #[derive(Debug)]
-pub struct TimeInterval(pub (NaiveDate, Option<NaiveTime>));
+pub struct TimeInterval(pub (NaiveDate, Option<DateTime<Tz>>));
 
 impl Display for TimeInterval {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} - ", self.0 .0)?;
         match self.0 .1 {
-            Some(time) => write!(f, "{}", time.format(TIME_FMT.fmt)),
-            None => write!(f, "All day"),
+            Some(time) => {
+                let time = time.with_timezone(&Local);
+                write!(
+                    f,
+                    "{} - {}",
+                    time.date_naive(),
+                    time.format(TIME_FMT.fmt)
+                )
+            }
+            None => write!(f, "{} - All day", self.0 .0),
         }?;
         Ok(())
     }
 }
 
 impl Deref for TimeInterval {
-    type Target = (NaiveDate, Option<NaiveTime>);
+    type Target = (NaiveDate, Option<DateTime<Tz>>);
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -29,7 +47,13 @@ pub struct AllRegexes {
     pub deadline: Regex,
     pub at_time: Regex,
     pub end: Regex,
+    pub for_duration: Regex,
     pub all_day: Regex,
+    pub tags: Regex,
+    pub priority: Regex,
+    pub callout: Regex,
+    pub timezone: Regex,
+    pub clock: Regex,
 }
 
 pub struct Format {
@@ -52,11 +76,94 @@ pub const DATETIME_FMT: Format = Format {
     fmt: "%Y-%m-%d %I:%M %p",
 };
 
-#[derive(Debug)]
+/// A problem found in a day file while parsing it.
+#[derive(Debug, Clone, Tabled)]
+pub struct Diagnostic {
+    #[tabled(rename = "Date")]
+    pub date: NaiveDate,
+    #[tabled(rename = "Line")]
+    pub line: usize,
+    #[tabled(rename = "Problem")]
+    pub message: String,
+    /// Whether `crs fmt` can fix the problem
+    #[tabled(rename = "Fixable")]
+    pub fixable: bool,
+}
+
+#[derive(Debug, Default)]
 pub struct Schedule {
     pub events: HashMap<String, CalEvent>,
     pub comments: Vec<Comment>,
     pub tbd_todos: Vec<ToDo>,
+    pub blockers: Vec<Comment>,
+    pub ideas: Vec<Comment>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub sessions: Vec<WorkSession>,
+    /// Paragraphs, headings and list items that are none of the entries.
+    pub prose: Vec<Prose>,
+    /// Metadata from the front matter of the day files that have one.
+    pub days: BTreeMap<NaiveDate, DayMeta>,
+    /// Timezone each parsed day was written in.
+    pub timezones: BTreeMap<NaiveDate, Tz>,
+    /// Names of the events that end on a day without starting in any of the
+    /// parsed days, by that day.
+    pub stray_ends: Vec<(NaiveDate, String)>,
+}
+
+/// YAML front matter at the top of a day file.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DayMeta {
+    pub mood: Option<String>,
+    pub location: Option<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    pub timezone: Option<String>,
+    /// Any other keys, kept as they were written.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml_ng::Value>,
+}
+
+/// Reads a list as well as a single value, which may list several values
+/// separated by commas, like `tags: work, travel`.
+fn one_or_many<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(values) => values
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .collect(),
+        OneOrMany::Many(values) => values,
+    })
+}
+
+impl Schedule {
+    /// Removes the `#tag` and `@context` tokens from the text of every todo
+    /// and comment. The parsed tags are kept. Event names are left alone, as
+    /// they key the events and could collide; the tables strip them instead.
+    pub fn strip_tags(&mut self, tag_search: &Regex) {
+        for todo in self.tbd_todos.iter_mut() {
+            todo.todo = strip_tags(&todo.todo, tag_search);
+        }
+        for comment in self
+            .comments
+            .iter_mut()
+            .chain(self.blockers.iter_mut())
+            .chain(self.ideas.iter_mut())
+        {
+            comment.comment = strip_tags(&comment.comment, tag_search);
+        }
+    }
 }
 
 #[derive(Debug, Tabled)]
@@ -65,32 +172,290 @@ pub struct CalEvent {
     pub start_time: TimeInterval,
     #[tabled(rename = "End Time")]
     pub end_time: TimeInterval,
-    // TODO: Figure out if description is feasible or not
-    // description: String,
+    #[tabled(rename = "Tags", display_with = "display_tags")]
+    pub tags: Vec<String>,
+    /// Lines of the schedule block following the title.
+    #[tabled(skip)]
+    pub description: String,
+    #[tabled(skip)]
+    pub location: Option<String>,
+    /// People the event is with.
+    #[tabled(skip)]
+    pub with: Vec<String>,
+    /// Line of the day file the event starts on.
+    #[tabled(skip)]
+    pub line: usize,
+    /// Short id that stays the same while the rest of the note changes.
+    #[tabled(skip)]
+    pub id: String,
+}
+
+impl CalEvent {
+    /// Time between the start and the end of the event. Events without a
+    /// start or an end time have none.
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.end_time.1? - self.start_time.1?)
+    }
+}
+
+/// Short id of an entry of the day, like `250106-3fa9`, hashed from what
+/// kind of entry it is and its text. Ids only change along with the entry
+/// itself, unlike line numbers. The date lets commands find the day again.
+///
+/// The hash is cut to 16 bits, plenty to tell the entries of one day apart.
+/// An entry that still clashes with an earlier one of its day is rehashed,
+/// so its id changes if the earlier one is removed.
+pub fn item_id(date: &NaiveDate, kind: &str, content: &str) -> String {
+    // FNV-1a, since the ids are written down and must hash the same on
+    // every build.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in kind.bytes().chain([0]).chain(content.trim().bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{}-{:04x}", date.format("%y%m%d"), hash & 0xffff)
+}
+
+/// Formats a duration as hours and minutes, e.g. `1h 30m`.
+pub fn display_duration(duration: &Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{sign}{minutes}m"),
+        (hours, 0) => format!("{sign}{hours}h"),
+        (hours, minutes) => format!("{sign}{hours}h {minutes}m"),
+    }
+}
+
+/// Free text of a day file.
+#[derive(Debug)]
+pub struct Prose {
+    pub date: NaiveDate,
+    pub line: usize,
+    pub text: String,
+}
+
+/// A span of work on a project, from a `CLOCK IN:` line to the next
+/// `CLOCK OUT:` line of the same day.
+#[derive(Debug)]
+pub struct WorkSession {
+    pub date: NaiveDate,
+    /// The first `#tag` of the `CLOCK IN:` line, without the sigil.
+    pub project: Option<String>,
+    pub start: DateTime<Tz>,
+    /// Unset while the session is still running.
+    pub end: Option<DateTime<Tz>>,
+    pub tags: Vec<String>,
+}
+
+impl WorkSession {
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.end? - self.start)
+    }
+}
+
+/// Time tracked on a project over a period.
+#[derive(Debug, PartialEq, Tabled)]
+pub struct TimeTotal {
+    #[tabled(rename = "Period")]
+    pub period: String,
+    #[tabled(rename = "Project")]
+    pub project: String,
+    #[tabled(rename = "Sessions")]
+    pub sessions: usize,
+    #[tabled(rename = "Tracked", display_with = "display_duration")]
+    pub tracked: Duration,
+}
+
+/// A problem with the times of one or more events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConflictKind {
+    /// Two events of the same day overlap
+    Overlap,
+    /// The event ends before it starts
+    EndsBeforeStart,
+    /// The event is still open at the end of the range
+    Unclosed,
+}
+
+impl Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictKind::Overlap => write!(f, "Overlap"),
+            ConflictKind::EndsBeforeStart => write!(f, "Ends before it starts"),
+            ConflictKind::Unclosed => write!(f, "Still open"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Tabled)]
+pub struct ScheduleConflict {
+    #[tabled(rename = "Date")]
+    pub date: NaiveDate,
+    #[tabled(rename = "Problem")]
+    pub kind: ConflictKind,
+    #[tabled(rename = "Events", display_with = "display_events")]
+    pub events: Vec<String>,
+}
+
+fn display_events(events: &[String]) -> String {
+    events.join(", ")
+}
+
+/// Time within the working hours without any events.
+#[derive(Debug, PartialEq, Tabled)]
+pub struct FreeSlot {
+    #[tabled(rename = "Date")]
+    pub date: NaiveDate,
+    #[tabled(rename = "From", display_with = "display_time")]
+    pub start: DateTime<Tz>,
+    #[tabled(rename = "Until", display_with = "display_time")]
+    pub end: DateTime<Tz>,
+    #[tabled(rename = "Free", display_with = "display_duration")]
+    pub length: Duration,
+}
+
+fn display_time(datetime: &DateTime<Tz>) -> String {
+    datetime
+        .with_timezone(&Local)
+        .format(TIME_FMT.fmt)
+        .to_string()
+}
+
+/// Hours scheduled over a day or a week.
+#[derive(Debug, PartialEq, Tabled)]
+pub struct ScheduleTotal {
+    #[tabled(rename = "Period")]
+    pub period: String,
+    #[tabled(rename = "Events")]
+    pub events: usize,
+    #[tabled(rename = "Scheduled", display_with = "display_duration")]
+    pub scheduled: Duration,
 }
 
 #[derive(Debug, Tabled)]
 pub struct Comment {
+    /// Day of the note the callout is in.
+    #[tabled(skip)]
+    pub date: NaiveDate,
     #[tabled(rename = "Time of Write", display_with = "display_datetime")]
-    pub time_of_write: NaiveDateTime,
+    pub time_of_write: Option<DateTime<Tz>>,
     #[tabled(rename = "Logs")]
     pub comment: String,
+    #[tabled(rename = "Tags", display_with = "display_tags")]
+    pub tags: Vec<String>,
+    /// Line of the day file the callout starts on.
+    #[tabled(skip)]
+    pub line: usize,
+    /// Short id that stays the same while the rest of the note changes.
+    #[tabled(skip)]
+    pub id: String,
 }
 
-fn display_datetime(datetime: &NaiveDateTime) -> String {
-    datetime.format(DATETIME_FMT.fmt).to_string()
+/// Shown in the zone of the viewer, which may differ from the zone the note
+/// was written in.
+fn display_datetime(datetime: &Option<DateTime<Tz>>) -> String {
+    match datetime {
+        Some(datetime) => datetime
+            .with_timezone(&Local)
+            .format(DATETIME_FMT.fmt)
+            .to_string(),
+        None => "None".to_string(),
+    }
 }
 
-#[derive(Debug, Tabled)]
+/// Attaches the zone to a wall clock time. Times skipped over by a daylight
+/// saving transition are moved an hour forward, past the gap, as the clocks
+/// were.
+pub fn localise(timezone: &Tz, datetime: NaiveDateTime) -> DateTime<Tz> {
+    timezone
+        .from_local_datetime(&datetime)
+        .earliest()
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(datetime + Duration::hours(1)))
+                .earliest()
+        })
+        .unwrap_or_else(|| timezone.from_utc_datetime(&datetime))
+}
+
+#[derive(Debug, Clone, Tabled)]
 pub struct ToDo {
     #[tabled(rename = "Date")]
     pub date: NaiveDate,
     #[tabled(rename = "Time of Write", display_with = "display_tow")]
     pub time_of_write: Option<NaiveTime>,
-    #[tabled(rename = "ToDo")]
+    #[tabled(rename = "ToDo", display_with("display_todo", self))]
     pub todo: String,
+    /// The todo text with its inline markdown formatting kept.
+    #[tabled(skip)]
+    pub markdown: String,
     #[tabled(rename = "Deadline", display_with = "display_deadline")]
     pub deadline: Option<NaiveDate>,
+    #[tabled(rename = "Priority", display_with = "display_priority")]
+    pub priority: Option<Priority>,
+    #[tabled(rename = "Tags", display_with = "display_tags")]
+    pub tags: Vec<String>,
+    /// Number of tasks this one is nested under. Top level tasks are at 0.
+    #[tabled(skip)]
+    pub depth: usize,
+    /// Completed and total count of the direct subtasks, if there are any.
+    #[tabled(skip)]
+    pub progress: Option<(usize, usize)>,
+    /// Id of the open todo this one is a subtask of.
+    #[tabled(skip)]
+    pub parent: Option<String>,
+    /// Line of the day file the todo is on.
+    #[tabled(skip)]
+    pub line: usize,
+    /// Short id that stays the same while the rest of the note changes.
+    #[tabled(skip)]
+    pub id: String,
+}
+
+/// Ordered from the most to the least urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Priority::High => write!(f, "High"),
+            Priority::Medium => write!(f, "Medium"),
+            Priority::Low => write!(f, "Low"),
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "a" | "high" => Ok(Priority::High),
+            "b" | "medium" => Ok(Priority::Medium),
+            "c" | "low" => Ok(Priority::Low),
+            _ => Err(format!("Unknown priority {s}")),
+        }
+    }
+}
+
+fn display_todo(todo: &ToDo) -> String {
+    let mut display = String::new();
+    if todo.depth > 0 {
+        display.push_str(&"↳".repeat(todo.depth));
+        display.push(' ');
+    }
+    display.push_str(todo.todo.trim());
+    if let Some((done, total)) = todo.progress {
+        display.push_str(&format!(" {done}/{total}"));
+    }
+    display
 }
 
 fn display_tow(tow: &Option<NaiveTime>) -> String {
@@ -107,6 +472,33 @@ fn display_deadline(tow: &Option<NaiveDate>) -> String {
     }
 }
 
+fn display_priority(priority: &Option<Priority>) -> String {
+    match priority {
+        Some(priority) => priority.to_string(),
+        None => "None".to_string(),
+    }
+}
+
+fn display_tags(tags: &[String]) -> String {
+    tags.join(" ")
+}
+
+/// Collects the `#tag` and `@context` tokens of a text, sigils included.
+pub fn find_tags(content: &str, tag_search: &Regex) -> Vec<String> {
+    tag_search
+        .captures_iter(content)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
+pub fn strip_tags(content: &str, tag_search: &Regex) -> String {
+    tag_search
+        .replace_all(content, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub fn init_regexes() -> AllRegexes {
     let mut deadline = r"DEADLINE: ".to_string();
     deadline.push_str(DATE_FMT.re);
@@ -120,12 +512,87 @@ pub fn init_regexes() -> AllRegexes {
     end.push_str(TIME_FMT.re);
     let end = Regex::new(&end).unwrap();
 
+    let for_duration =
+        Regex::new(r"FOR:\s*([0-9]+[wdhm](?:\s*[0-9]+[wdhm])*)\b").unwrap();
+
     let all_day = Regex::new(r"ALL DAY").unwrap();
 
+    let tags = Regex::new(r"(?:^|\s)([#@][\w/-]+)").unwrap();
+
+    let priority =
+        Regex::new(r"^\s*\(([ABC])\)(?:\s|$)|PRIORITY: (?i:(high|medium|low))")
+            .unwrap();
+
+    // Lenient on purpose: a missing `!`, a swapped `!` and `[`, stray
+    // whitespace and any casing are all accepted and fixed up.
+    let callout = Regex::new(
+        r"(?i)^((?:\s*>)+)\s*(?:!\s*\[\s*!?|\[\s*!?)\s*(note|tip|important|warning|caution)\s*\]\s*!?\s*$",
+    )
+    .unwrap();
+
+    let timezone = Regex::new(r"(?m)^TZ:\s*(\S+)\s*$").unwrap();
+
+    let clock =
+        Regex::new(&format!(r"(?m)^CLOCK (IN|OUT): ({})(.*)$", TIME_FMT.re))
+            .unwrap();
+
     AllRegexes {
         deadline,
         at_time,
         end,
+        for_duration,
         all_day,
+        tags,
+        priority,
+        callout,
+        timezone,
+        clock,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono_tz::America::New_York;
+
+    #[test]
+    fn check_localise() {
+        let at = |day, hour, minute| {
+            NaiveDate::from_ymd_opt(2025, 3, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+        };
+
+        // 02:00 to 03:00 never happened, as the clocks went forward.
+        let gap = localise(&New_York, at(9, 2, 30));
+        assert_eq!(gap.naive_local(), at(9, 3, 30));
+        assert_eq!(gap.naive_utc(), at(9, 7, 30));
+
+        let before = localise(&New_York, at(9, 1, 30));
+        assert_eq!(before.naive_utc(), at(9, 6, 30));
+    }
+
+    #[test]
+    fn check_display_duration() {
+        for (minutes, display) in [
+            (30, "30m"),
+            (120, "2h"),
+            (90, "1h 30m"),
+            (-90, "-1h 30m"),
+            (-30, "-30m"),
+        ] {
+            assert_eq!(display_duration(&Duration::minutes(minutes)), display);
+        }
+
+        let for_duration = init_regexes().for_duration;
+        for (text, duration) in [
+            ("Retro FOR: 1h30m", "1h30m"),
+            ("Retro FOR: 1h 30m", "1h 30m"),
+            ("Retro FOR:45m with 2 people", "45m"),
+            ("Retro FOR: 1h 2days", "1h"),
+        ] {
+            assert_eq!(&for_duration.captures(text).unwrap()[1], duration);
+        }
     }
 }
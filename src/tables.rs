@@ -2,17 +2,22 @@ use std::collections::HashMap;
 
 use crate::{
     config::{CommentConfig, ScheduleConfig, TodoConfig},
-    structs::{CalEvent, Comment, ToDo},
+    structs::{
+        self, display_duration, init_regexes, CalEvent, Comment, Priority, ToDo,
+    },
 };
 
+use chrono::NaiveDate;
 use tabled::{
+    builder::Builder,
     settings::{
         formatting::{AlignmentStrategy, TrimStrategy},
+        object::Rows,
         peaker::PriorityMax,
         style::HorizontalLine,
-        Alignment, Format, Margin, Theme, Width,
+        Alignment, Color, Format, Margin, Theme, Width,
     },
-    Table,
+    Table, Tabled,
 };
 
 macro_rules! configure_table {
@@ -43,39 +48,300 @@ pub fn print_schedule(
     cal_events: &HashMap<String, CalEvent>,
     config: &ScheduleConfig,
     width: u16,
+    colour: bool,
+    strip_tags: bool,
+) {
+    println!(
+        "{}",
+        schedule_table(cal_events, config, width, colour, strip_tags)
+    );
+}
+
+/// Builds the table [`print_schedule`] prints.
+pub fn schedule_table(
+    cal_events: &HashMap<String, CalEvent>,
+    config: &ScheduleConfig,
+    width: u16,
+    colour: bool,
+    strip_tags: bool,
+) -> Table {
+    let mut theme: Theme = config.table_style.into();
+    configure_theme(&mut theme);
+
+    // Collected so that the row indices stay stable while colouring.
+    let cal_events = event_names(cal_events, strip_tags);
+
+    let mut builder = Table::builder(&cal_events);
+    let end_col = 1 + CalEvent::headers()
+        .iter()
+        .position(|header| header == "End Time")
+        .expect("CalEvent has an end time column");
+    builder.insert_column(
+        end_col + 1,
+        std::iter::once("Duration".to_string())
+            .chain(cal_events.iter().map(|(_, event)| duration_cell(event))),
+    );
+
+    let name_col = usize::from(config.show_ids);
+    if config.show_ids {
+        id_column(&mut builder, cal_events.iter().map(|(_, event)| &event.id));
+    }
+
+    let mut table = builder.build();
+    table.modify((0, name_col), Format::content(|_| "Schedule".to_string()));
+    configure_table!(table, theme, config, width);
+
+    if colour {
+        let all_day: Color = config.all_day_colour.into();
+        for (row, (_, event)) in cal_events.iter().enumerate() {
+            if event.start_time.1.is_none() {
+                table.modify(Rows::single(row + 1), &all_day);
+            }
+        }
+        table.modify(Rows::first(), Color::from(config.header_colour));
+    }
+
+    table
+}
+
+/// Prints every event on its own row along with its description, location
+/// and the people it is with.
+pub fn print_event_details(
+    cal_events: &HashMap<String, CalEvent>,
+    config: &ScheduleConfig,
+    width: u16,
+    colour: bool,
+    strip_tags: bool,
+) {
+    println!(
+        "{}",
+        event_details_table(cal_events, config, width, colour, strip_tags)
+    );
+}
+
+/// Builds the table [`print_event_details`] prints.
+pub fn event_details_table(
+    cal_events: &HashMap<String, CalEvent>,
+    config: &ScheduleConfig,
+    width: u16,
+    colour: bool,
+    strip_tags: bool,
+) -> Table {
+    let mut theme: Theme = config.table_style.into();
+    configure_theme(&mut theme);
+
+    let mut cal_events = event_names(cal_events, strip_tags);
+    cal_events.sort_by(|(a_name, a), (b_name, b)| {
+        (*a.start_time, a_name).cmp(&(*b.start_time, b_name))
+    });
+
+    let mut builder = Builder::default();
+    builder.push_record([
+        "Event",
+        "Start Time",
+        "End Time",
+        "Duration",
+        "Location",
+        "With",
+        "Description",
+    ]);
+    for (name, event) in &cal_events {
+        builder.push_record([
+            name.to_string(),
+            event.start_time.to_string(),
+            event.end_time.to_string(),
+            duration_cell(event),
+            event.location.clone().unwrap_or_default(),
+            event.with.join(", "),
+            event.description.clone(),
+        ]);
+    }
+    if config.show_ids {
+        id_column(&mut builder, cal_events.iter().map(|(_, event)| &event.id));
+    }
+
+    let mut table = builder.build();
+    configure_table!(table, theme, config, width);
+
+    if colour {
+        let all_day: Color = config.all_day_colour.into();
+        for (row, (_, event)) in cal_events.iter().enumerate() {
+            if event.start_time.1.is_none() {
+                table.modify(Rows::single(row + 1), &all_day);
+            }
+        }
+        table.modify(Rows::first(), Color::from(config.header_colour));
+    }
+
+    table
+}
+
+/// Prints a table derived from the schedule, like its totals or conflicts.
+pub fn print_summary<T: Tabled>(
+    totals: &[T],
+    config: &ScheduleConfig,
+    width: u16,
+    colour: bool,
 ) {
+    println!("{}", summary_table(totals, config, width, colour));
+}
+
+/// Builds the table [`print_summary`] prints.
+pub fn summary_table<T: Tabled>(
+    totals: &[T],
+    config: &ScheduleConfig,
+    width: u16,
+    colour: bool,
+) -> Table {
     let mut theme: Theme = config.table_style.into();
     configure_theme(&mut theme);
 
-    let mut table = Table::new(cal_events);
-    table.modify((0, 0), Format::content(|_| "Schedule".to_string()));
+    let mut table = Table::new(totals);
     configure_table!(table, theme, config, width);
 
-    println!("{table}");
+    if colour {
+        table.modify(Rows::first(), Color::from(config.header_colour));
+    }
+
+    table
+}
+
+fn id_column<'a>(builder: &mut Builder, ids: impl Iterator<Item = &'a String>) {
+    builder.insert_column(
+        0,
+        std::iter::once("Id".to_string()).chain(ids.cloned()),
+    );
+}
+
+/// Pairs the events with the names they are shown with. The names are only
+/// stripped here, as two events can share a name once their tags are gone.
+fn event_names(
+    cal_events: &HashMap<String, CalEvent>,
+    strip_tags: bool,
+) -> Vec<(String, &CalEvent)> {
+    let tag_search = strip_tags.then(|| init_regexes().tags);
+    cal_events
+        .iter()
+        .map(|(name, event)| match &tag_search {
+            Some(tag_search) => (structs::strip_tags(name, tag_search), event),
+            None => (name.clone(), event),
+        })
+        .collect()
+}
+
+fn duration_cell(event: &CalEvent) -> String {
+    event
+        .duration()
+        .map(|duration| display_duration(&duration))
+        .unwrap_or_default()
+}
+
+pub fn print_todos(
+    todos: &Vec<ToDo>,
+    config: &TodoConfig,
+    width: u16,
+    colour: bool,
+    today: NaiveDate,
+) {
+    println!("{}", todos_table(todos, config, width, colour, today));
 }
 
-pub fn print_todos(todos: &Vec<ToDo>, config: &TodoConfig, width: u16) {
+/// Builds the table [`print_todos`] prints.
+pub fn todos_table(
+    todos: &Vec<ToDo>,
+    config: &TodoConfig,
+    width: u16,
+    colour: bool,
+    today: NaiveDate,
+) -> Table {
     let mut theme: Theme = config.table_style.into();
     configure_theme(&mut theme);
 
-    let mut table = Table::new(todos);
+    let mut builder = Table::builder(todos);
+    if config.show_ids {
+        id_column(&mut builder, todos.iter().map(|todo| &todo.id));
+    }
+    let mut table = builder.build();
     configure_table!(table, theme, config, width);
 
-    println!("{table}");
+    if colour {
+        let overdue: Color = config.overdue_colour.into();
+        let due_today: Color = config.due_today_colour.into();
+        let priority_col = usize::from(config.show_ids)
+            + ToDo::headers()
+                .iter()
+                .position(|header| header == "Priority")
+                .expect("ToDo has a priority column");
+        for (row, todo) in todos.iter().enumerate() {
+            match todo.deadline {
+                Some(deadline) if deadline < today => {
+                    table.modify(Rows::single(row + 1), &overdue);
+                }
+                Some(deadline) if deadline == today => {
+                    table.modify(Rows::single(row + 1), &due_today);
+                }
+                _ => (),
+            }
+
+            let priority: Option<Color> = match todo.priority {
+                Some(Priority::High) => {
+                    Some(config.high_priority_colour.into())
+                }
+                Some(Priority::Medium) => {
+                    Some(config.medium_priority_colour.into())
+                }
+                Some(Priority::Low) => Some(config.low_priority_colour.into()),
+                None => None,
+            };
+            if let Some(priority) = priority {
+                table.modify((row + 1, priority_col), priority);
+            }
+        }
+        table.modify(Rows::first(), Color::from(config.header_colour));
+    }
+
+    table
 }
 
 pub fn print_comments(
     comments: &Vec<Comment>,
     config: &CommentConfig,
     width: u16,
+    colour: bool,
 ) {
+    println!("{}", comments_table(comments, config, width, colour));
+}
+
+/// Builds the table [`print_comments`] prints.
+pub fn comments_table(
+    comments: &Vec<Comment>,
+    config: &CommentConfig,
+    width: u16,
+    colour: bool,
+) -> Table {
     let mut theme: Theme = config.table_style.into();
     configure_theme(&mut theme);
 
-    let mut table = Table::new(comments);
+    let heading = config.heading.clone();
+    let heading_col = usize::from(config.show_ids)
+        + Comment::headers()
+            .iter()
+            .position(|header| header == "Logs")
+            .expect("Comment has a logs column");
+
+    let mut builder = Table::builder(comments);
+    if config.show_ids {
+        id_column(&mut builder, comments.iter().map(|comment| &comment.id));
+    }
+    let mut table = builder.build();
+    table.modify((0, heading_col), Format::content(|_| heading.clone()));
     configure_table!(table, theme, config, width);
 
-    println!("{table}");
+    if colour {
+        table.modify(Rows::first(), Color::from(config.header_colour));
+    }
+
+    table
 }
 
 fn configure_theme(theme: &mut Theme) {
@@ -97,14 +363,18 @@ fn configure_theme(theme: &mut Theme) {
 mod test {
     use super::*;
     use crate::config::{
-        CommentConfigBuilder, ScheduleConfigBuilder, TableStyle,
+        CalloutConfig, CommentConfigBuilder, ScheduleConfigBuilder, TableStyle,
         TodoConfigBuilder,
     };
     use crate::parse::*;
     use chrono::NaiveDate;
+    use chrono_tz::Tz;
     use std::path::PathBuf;
     use std::str::FromStr;
 
+    const BOLD: &str = "\u{1b}[1m";
+    const DIMMED: &str = "\u{1b}[2m";
+
     #[test]
     fn check_events() {
         let start_date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
@@ -114,32 +384,109 @@ mod test {
             &start_date,
             &end_date,
             PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
         );
 
-        print_schedule(
-            &sched.events,
-            &ScheduleConfigBuilder::default()
-                .table_style(TableStyle::Empty)
+        for style in [TableStyle::Empty, TableStyle::Extended] {
+            let config = ScheduleConfigBuilder::default()
+                .table_style(style)
                 .build()
-                .unwrap(),
-            80,
+                .unwrap();
+            let table =
+                schedule_table(&sched.events, &config, 80, false, false);
+            assert!(!table.to_string().contains('\u{1b}'));
+        }
+
+        let config = ScheduleConfigBuilder::default()
+            .table_style(TableStyle::Rounded)
+            .build()
+            .unwrap();
+        let table = schedule_table(&sched.events, &config, 200, true, false);
+        let lines: Vec<String> =
+            table.to_string().lines().map(String::from).collect();
+        assert!(lines.iter().any(|line| line.contains(BOLD)));
+        let all_day = sched
+            .events
+            .iter()
+            .filter(|(_, event)| event.start_time.1.is_none())
+            .count();
+        assert!(all_day > 0);
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.contains(DIMMED) && !line.contains(BOLD))
+                .count(),
+            all_day
         );
-        print_schedule(
-            &sched.events,
-            &ScheduleConfigBuilder::default()
-                .table_style(TableStyle::Extended)
-                .build()
-                .unwrap(),
-            80,
+    }
+
+    #[test]
+    fn check_event_details() {
+        let start_date = NaiveDate::from_ymd_opt(2025, 1, 9).unwrap();
+        let end_date = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+
+        let sched = parse_sequence(
+            &start_date,
+            &end_date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
         );
-        print_schedule(
+
+        let table = event_details_table(
+            &sched.events,
+            &ScheduleConfigBuilder::default().build().unwrap(),
+            200,
+            true,
+            false,
+        )
+        .to_string();
+        assert!(table.contains(BOLD));
+        assert!(table.contains("Pairing session"));
+
+        let table = schedule_table(
             &sched.events,
             &ScheduleConfigBuilder::default()
-                .table_style(TableStyle::Rounded)
+                .show_ids(true)
                 .build()
                 .unwrap(),
-            80,
-        );
+            200,
+            false,
+            false,
+        )
+        .to_string();
+        for event in sched.events.values() {
+            assert!(table.contains(&event.id));
+        }
+    }
+
+    #[test]
+    fn check_stripped_names() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let standup = || {
+            let mut sched = parse_sequence(
+                &date,
+                &date.succ_opt().unwrap(),
+                PathBuf::from_str("tests").as_mut().unwrap(),
+                &CalloutConfig::default(),
+                &Tz::UTC,
+            );
+            sched.events.remove("Standup #work @office").unwrap()
+        };
+        let events = HashMap::from([
+            ("Sync #a".to_string(), standup()),
+            ("Sync #b".to_string(), standup()),
+        ]);
+
+        let config = ScheduleConfigBuilder::default().build().unwrap();
+        let table =
+            schedule_table(&events, &config, 80, false, true).to_string();
+        assert_eq!(table.matches("Sync").count(), 2);
+        assert!(!table.contains("#a"));
+        let table = event_details_table(&events, &config, 200, false, false)
+            .to_string();
+        assert!(table.contains("Sync #a") && table.contains("Sync #b"));
     }
 
     #[test]
@@ -151,21 +498,40 @@ mod test {
             &start_date,
             &end_date,
             PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
         );
 
-        print_todos(
-            &sched.tbd_todos,
-            &TodoConfigBuilder::default().build().unwrap(),
-            80,
+        let config = TodoConfigBuilder::default()
+            .table_style(TableStyle::Extended)
+            .build()
+            .unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let table = todos_table(&sched.tbd_todos, &config, 80, false, today);
+        assert!(!table.to_string().contains('\u{1b}'));
+
+        // Every deadline in the fixtures has passed.
+        let table = todos_table(&sched.tbd_todos, &config, 200, true, today);
+        let overdue = sched
+            .tbd_todos
+            .iter()
+            .filter(|todo| todo.deadline.is_some())
+            .count();
+        assert!(overdue > 0);
+        assert_eq!(
+            table.to_string().matches("\u{1b}[31m").count(),
+            overdue * ToDo::LENGTH
         );
-        print_todos(
+
+        let table = todos_table(
             &sched.tbd_todos,
-            &TodoConfigBuilder::default()
-                .table_style(TableStyle::Extended)
-                .build()
-                .unwrap(),
-            80,
-        );
+            &TodoConfigBuilder::default().show_ids(true).build().unwrap(),
+            200,
+            false,
+            today,
+        )
+        .to_string();
+        assert!(table.lines().next().unwrap().contains("Id"));
     }
 
     #[test]
@@ -177,21 +543,44 @@ mod test {
             &start_date,
             &end_date,
             PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
         );
 
-        print_comments(
-            &sched.comments,
-            &CommentConfigBuilder::default().build().unwrap(),
-            80,
+        let config = CommentConfigBuilder::default()
+            .table_style(TableStyle::Extended)
+            .build()
+            .unwrap();
+        let table = comments_table(&sched.comments, &config, 80, false);
+        assert!(!table.to_string().contains('\u{1b}'));
+        let table = comments_table(&sched.comments, &config, 80, true);
+        let header = table.to_string().lines().nth(1).unwrap().to_string();
+        assert!(header.contains(BOLD));
+        assert!(header.contains("Logs"));
+    }
+
+    #[test]
+    fn check_blockers() {
+        let start_date = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        let end_date = NaiveDate::from_ymd_opt(2025, 1, 11).unwrap();
+
+        let sched = parse_sequence(
+            &start_date,
+            &end_date,
+            PathBuf::from_str("tests").as_mut().unwrap(),
+            &CalloutConfig::default(),
+            &Tz::UTC,
         );
 
-        print_comments(
-            &sched.comments,
+        let table = comments_table(
+            &sched.blockers,
             &CommentConfigBuilder::default()
-                .table_style(TableStyle::Extended)
+                .heading("Blockers".to_string())
                 .build()
                 .unwrap(),
             80,
+            false,
         );
+        assert!(table.to_string().contains("Blockers"));
     }
 }
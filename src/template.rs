@@ -0,0 +1,187 @@
+use std::{fs, path::Path};
+
+use crate::config::UpperConfig;
+use crate::edit::todo_line;
+use crate::parse::{day_filename, parse_sequence};
+use crate::structs::{CalEvent, ToDo, DATE_FMT, TIME_FMT};
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+
+/// What a new day looks like when no template is configured.
+pub const DEFAULT_DAY_TEMPLATE: &str = "# {{weekday}} {{date}}\n";
+
+/// Fills in the placeholders of a day template. `carried_todos` are written
+/// as open tasks and `events_today` as one line per event.
+pub fn render_day(
+    template: &str,
+    date: NaiveDate,
+    carried_todos: &[&ToDo],
+    events_today: &[(&String, &CalEvent)],
+) -> String {
+    let todos: Vec<String> =
+        carried_todos.iter().map(|todo| todo_line(todo)).collect();
+    let events: Vec<String> = events_today
+        .iter()
+        .map(|(name, event)| match event.start_time.0 .1 {
+            Some(time) => format!("- {} {name}", time.format(TIME_FMT.fmt)),
+            None => format!("- ALL DAY {name}"),
+        })
+        .collect();
+
+    template
+        .replace("{{date}}", &date.format(DATE_FMT.fmt).to_string())
+        .replace("{{weekday}}", &date.format("%A").to_string())
+        .replace("{{carried_todos}}", &todos.join("\n"))
+        .replace("{{events_today}}", &events.join("\n"))
+}
+
+/// Creates the note of the day in the notes folder from the configured
+/// template, unless it already exists. `carried_todos` fill in
+/// `{{carried_todos}}`, and marking their originals as migrated is left to
+/// the caller. As the day has no note yet, the only events `{{events_today}}`
+/// can list are the ones of the days before that run into it. Returns
+/// whether the note was created.
+pub fn create_day(
+    notes: &Path,
+    date: NaiveDate,
+    config: &UpperConfig,
+    carried_todos: &[&ToDo],
+) -> Result<bool> {
+    let path = notes.join(day_filename(&date));
+    if path.exists() {
+        return Ok(false);
+    }
+
+    let template = match &config.templates.day {
+        Some(template) => {
+            let template = notes.join(template);
+            fs::read_to_string(&template).with_context(|| {
+                format!("Failed to read the template {}", template.display())
+            })?
+        }
+        None => DEFAULT_DAY_TEMPLATE.to_string(),
+    };
+
+    let schedule = parse_sequence(
+        &config.start_date.min(date),
+        &date.succ_opt().expect("Not the end of time"),
+        &mut notes.to_path_buf(),
+        &config.callouts,
+        &config.timezone(),
+    );
+    let mut events_today: Vec<(&String, &CalEvent)> = schedule
+        .events
+        .iter()
+        .filter(|(_, event)| {
+            event.start_time.0 .0 <= date && date <= event.end_time.0 .0
+        })
+        .collect();
+    events_today.sort_by_key(|(name, event)| (event.start_time.0 .1, *name));
+
+    fs::create_dir_all(notes)
+        .with_context(|| format!("Failed to create {}", notes.display()))?;
+    fs::write(
+        &path,
+        render_day(&template, date, carried_todos, &events_today),
+    )
+    .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{
+        CommentConfig, ScheduleConfig, TemplateConfigBuilder, TodoConfig,
+        UpperConfigBuilder,
+    };
+    use chrono_tz::Tz;
+    use std::path::PathBuf;
+
+    fn config(day: Option<PathBuf>) -> UpperConfig {
+        UpperConfigBuilder::default()
+            .start_date(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+            .timezone(Some(Tz::UTC))
+            .templates(
+                TemplateConfigBuilder::default().day(day).build().unwrap(),
+            )
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn check_create_day() {
+        let notes = tempfile::tempdir().unwrap();
+        let notes = notes.path();
+        let monday = "- [ ] Ship it #work DEADLINE: 2025-01-10\n  - [ ] Tag **it** PRIORITY: HIGH\n- [x] Done\n\n\
+                      > [!IMPORTANT]\n> Offsite AT: 09:00 AM FOR: 2d\n";
+        fs::write(notes.join("2025-01-06.md"), monday).unwrap();
+        fs::write(
+            notes.join("template.md"),
+            "# {{weekday}}, {{date}}\n\n{{events_today}}\n\n{{carried_todos}}\n",
+        )
+        .unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2025, 1, 7).unwrap();
+        let path = notes.join("2025-01-07.md");
+        assert!(create_day(notes, date, &config(None), &[]).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Tuesday 2025-01-07\n"
+        );
+        assert!(!create_day(notes, date, &config(None), &[]).unwrap());
+
+        // The offsite runs into the new day, and no todo is carried.
+        fs::remove_file(&path).unwrap();
+        let config = config(Some(PathBuf::from("template.md")));
+        assert!(create_day(notes, date, &config, &[]).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Tuesday, 2025-01-07\n\n- 09:00 AM Offsite\n\n\n"
+        );
+
+        // Carried todos are placed, and their originals left to the caller.
+        let date = NaiveDate::from_ymd_opt(2025, 1, 9).unwrap();
+        let schedule = parse_sequence(
+            &config.start_date,
+            &date,
+            &mut notes.to_path_buf(),
+            &config.callouts,
+            &Tz::UTC,
+        );
+        let carried: Vec<&ToDo> = schedule.tbd_todos.iter().collect();
+        assert!(create_day(notes, date, &config, &carried).unwrap());
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-09.md")).unwrap(),
+            "# Thursday, 2025-01-09\n\n\n\n- [ ] Ship it #work DEADLINE: 2025-01-10\n  - [ ] Tag **it** PRIORITY: high\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("2025-01-06.md")).unwrap(),
+            monday
+        );
+    }
+
+    #[test]
+    fn check_render_day() {
+        let config = config(None);
+        let date = NaiveDate::from_ymd_opt(2025, 1, 9).unwrap();
+        let schedule = parse_sequence(
+            &date,
+            &date.succ_opt().unwrap(),
+            &mut PathBuf::from("tests"),
+            &config.callouts,
+            &Tz::UTC,
+        );
+        let mut events: Vec<_> = schedule.events.iter().collect();
+        events.sort_by_key(|(name, event)| (event.start_time.0 .1, *name));
+
+        assert_eq!(
+            render_day("{{events_today}}", date, &[], &events),
+            "- ALL DAY Offsite\n- 10:00 AM Pairing session\n- 02:00 PM Design review"
+        );
+    }
+}
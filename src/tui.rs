@@ -0,0 +1,687 @@
+use std::path::PathBuf;
+
+use crate::config::UpperConfig;
+use crate::edit::{add_todo, complete_todo};
+use crate::parse::{day_filename, parse_date, parse_sequence, parse_time};
+use crate::query::TagQuery;
+use crate::reschedule::{reschedule, ItemRef};
+use crate::structs::{CalEvent, Comment, Priority, Schedule, ToDo};
+use crate::template::create_day;
+
+use anyhow::Result;
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveTime, Weekday};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Row, Table, TableState, Tabs},
+    Frame,
+};
+use tabled::Tabled;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tab {
+    Todos,
+    Schedule,
+    Logs,
+    Calendar,
+}
+
+impl Tab {
+    const ALL: [Tab; 4] = [Tab::Todos, Tab::Schedule, Tab::Logs, Tab::Calendar];
+
+    fn index(self) -> usize {
+        Tab::ALL
+            .iter()
+            .position(|tab| *tab == self)
+            .expect("Listed")
+    }
+}
+
+/// A line being typed in at the bottom of the screen.
+#[derive(Debug, PartialEq)]
+enum Input {
+    AddTodo(String),
+    Reschedule(String),
+}
+
+/// State of `crs tui`. Shows one day at a time and writes every change
+/// straight back to the notes.
+pub struct App<'a> {
+    config: &'a UpperConfig,
+    notes: PathBuf,
+    colour: bool,
+    /// The `--tag` and `--context` filters
+    filter: TagQuery,
+    pub day: NaiveDate,
+    today: NaiveDate,
+    pub tab: Tab,
+    selected: usize,
+    pub schedule: Schedule,
+    input: Option<Input>,
+    pub message: Option<String>,
+    pub quit: bool,
+}
+
+impl<'a> App<'a> {
+    pub fn new(
+        config: &'a UpperConfig,
+        notes: PathBuf,
+        today: NaiveDate,
+        colour: bool,
+        filter: TagQuery,
+    ) -> Self {
+        let mut app = App {
+            config,
+            notes,
+            colour,
+            filter,
+            day: today,
+            today,
+            tab: Tab::Todos,
+            selected: 0,
+            schedule: Schedule::default(),
+            input: None,
+            message: None,
+            quit: false,
+        };
+        app.reload();
+        app
+    }
+
+    /// Parses the note of the shown day again.
+    fn reload(&mut self) {
+        self.schedule = parse_sequence(
+            &self.day,
+            &self.day.succ_opt().expect("Not the end of time"),
+            &mut self.notes,
+            &self.config.callouts,
+            &self.config.timezone(),
+        );
+        self.filter.apply(&mut self.schedule);
+        self.selected = self.selected.min(self.rows().saturating_sub(1));
+    }
+
+    fn go_to(&mut self, day: NaiveDate) {
+        self.day = day;
+        self.selected = 0;
+        self.reload();
+    }
+
+    fn rows(&self) -> usize {
+        match self.tab {
+            Tab::Todos => self.schedule.tbd_todos.len(),
+            Tab::Schedule => self.schedule.events.len(),
+            Tab::Logs => {
+                self.schedule.comments.len()
+                    + self.schedule.blockers.len()
+                    + self.schedule.ideas.len()
+            }
+            Tab::Calendar => 0,
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.notes.join(day_filename(&self.day))
+    }
+
+    fn selected_todo(&self) -> Option<&ToDo> {
+        match self.tab {
+            Tab::Todos => self.schedule.tbd_todos.get(self.selected),
+            _ => None,
+        }
+    }
+
+    /// Events of the day in the order the schedule tab lists them.
+    fn events(&self) -> Vec<(&String, &CalEvent)> {
+        let mut events: Vec<(&String, &CalEvent)> =
+            self.schedule.events.iter().collect();
+        events.sort_by_key(|(name, event)| (*event.start_time, *name));
+        events
+    }
+
+    fn selected_event(&self) -> Option<(&String, &CalEvent)> {
+        match self.tab {
+            Tab::Schedule => self.events().get(self.selected).copied(),
+            _ => None,
+        }
+    }
+
+    /// The todo or the event selected, to reschedule.
+    fn selected_item(&self) -> Option<ItemRef> {
+        let (date, line) = match (self.selected_todo(), self.selected_event()) {
+            (Some(todo), _) => (todo.date, todo.line),
+            (None, Some((_, event))) => (event.start_time.0 .0, event.line),
+            (None, None) => return None,
+        };
+        Some(ItemRef::Line { date, line })
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) {
+        self.message = None;
+        if self.input.is_some() {
+            self.handle_input(key);
+            return;
+        }
+
+        let calendar = self.tab == Tab::Calendar;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => {
+                self.tab = Tab::ALL[(self.tab.index() + 1) % Tab::ALL.len()];
+                self.selected = 0;
+            }
+            KeyCode::BackTab => {
+                self.tab = Tab::ALL
+                    [(self.tab.index() + Tab::ALL.len() - 1) % Tab::ALL.len()];
+                self.selected = 0;
+            }
+            KeyCode::Char(chr @ '1'..='4') => {
+                self.tab = Tab::ALL[chr as usize - '1' as usize];
+                self.selected = 0;
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.go_to(self.day.pred_opt().expect("Not the dawn of time"))
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.go_to(self.day.succ_opt().expect("Not the end of time"))
+            }
+            KeyCode::Up | KeyCode::Char('k') if calendar => {
+                if let Some(day) = self.day.checked_sub_days(Days::new(7)) {
+                    self.go_to(day)
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') if calendar => {
+                if let Some(day) = self.day.checked_add_days(Days::new(7)) {
+                    self.go_to(day)
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Down | KeyCode::Char('j')
+                if self.selected + 1 < self.rows() =>
+            {
+                self.selected += 1
+            }
+            KeyCode::Enter if calendar => self.tab = Tab::Todos,
+            KeyCode::Char('t') => self.go_to(self.today),
+            KeyCode::Char('a') => {
+                self.input = Some(Input::AddTodo(String::new()))
+            }
+            KeyCode::Char('x' | ' ') => {
+                let Some(line) = self.selected_todo().map(|todo| todo.line)
+                else {
+                    self.message = Some("No todo selected".to_string());
+                    return;
+                };
+                let done = complete_todo(&self.path(), line);
+                self.finish(done, "Marked the todo done");
+            }
+            KeyCode::Char('r') => {
+                if self.selected_item().is_some() {
+                    self.input = Some(Input::Reschedule(String::new()));
+                } else {
+                    self.message =
+                        Some("No todo or event selected".to_string());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_input(&mut self, key: KeyEvent) {
+        let (Some(Input::AddTodo(text)) | Some(Input::Reschedule(text))) =
+            self.input.as_mut()
+        else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.input = None,
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(chr) => text.push(chr),
+            KeyCode::Enter => match self.input.take() {
+                Some(Input::AddTodo(text)) if !text.trim().is_empty() => {
+                    let added =
+                        create_day(&self.notes, self.day, self.config, &[])
+                            .and_then(|_| add_todo(&self.path(), &text));
+                    self.finish(added, "Added the todo");
+                }
+                Some(Input::Reschedule(text)) => {
+                    let Some((date, at)) = parse_when(&text, self.today) else {
+                        self.message = Some(format!("Not a date: {text}"));
+                        return;
+                    };
+                    let Some(item) = self.selected_item() else {
+                        return;
+                    };
+                    let done = match self.selected_event() {
+                        Some((name, _)) => format!("Moved {name} to {date}"),
+                        None => format!("Due {date}"),
+                    };
+                    let moved =
+                        reschedule(&self.notes, &item, date, at, self.config);
+                    self.finish(moved.map(|_| ()), &done);
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    fn finish(&mut self, result: Result<()>, done: &str) {
+        self.message = Some(match result {
+            Ok(()) => done.to_string(),
+            Err(e) => e.to_string(),
+        });
+        self.reload();
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let [tabs, body, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let title = format!(" {} {} ", self.day.weekday(), self.day);
+        frame.render_widget(
+            Tabs::new(["Todos", "Schedule", "Logs", "Calendar"])
+                .select(self.tab.index())
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                .block(Block::bordered().title(title)),
+            tabs,
+        );
+
+        match self.tab {
+            Tab::Todos => self.render_todos(frame, body),
+            Tab::Schedule => self.render_schedule(frame, body),
+            Tab::Logs => self.render_logs(frame, body),
+            Tab::Calendar => self.render_calendar(frame, body),
+        }
+
+        let status_line = match &self.input {
+            Some(Input::AddTodo(text)) => format!("New todo: {text}"),
+            Some(Input::Reschedule(text)) => format!("Reschedule to: {text}"),
+            None => self.message.clone().unwrap_or(
+                "tab switch  ←→ day  ↑↓ select  t today  a add  x done  r reschedule  q quit"
+                    .to_string(),
+            ),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn style(&self, style: impl Into<Style>) -> Style {
+        if self.colour {
+            style.into()
+        } else {
+            Style::new()
+        }
+    }
+
+    fn render_table(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        headers: Vec<String>,
+        rows: Vec<Row>,
+        header_style: Style,
+    ) {
+        let widths = vec![Constraint::Fill(1); headers.len()];
+        let table = Table::new(rows, widths)
+            .header(Row::new(headers).style(header_style))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered());
+        let mut state = TableState::new().with_selected(Some(self.selected));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn render_todos(&self, frame: &mut Frame, area: Rect) {
+        let todos = &self.config.todos;
+        let rows = self
+            .schedule
+            .tbd_todos
+            .iter()
+            .map(|todo| {
+                let style = match todo.deadline {
+                    Some(date) if date < self.today => {
+                        self.style(todos.overdue_colour)
+                    }
+                    Some(date) if date == self.today => {
+                        self.style(todos.due_today_colour)
+                    }
+                    _ => match todo.priority {
+                        Some(Priority::High) => {
+                            self.style(todos.high_priority_colour)
+                        }
+                        Some(Priority::Medium) => {
+                            self.style(todos.medium_priority_colour)
+                        }
+                        Some(Priority::Low) => {
+                            self.style(todos.low_priority_colour)
+                        }
+                        None => Style::new(),
+                    },
+                };
+                Row::new(todo.fields()).style(style)
+            })
+            .collect();
+        self.render_table(
+            frame,
+            area,
+            column_names::<ToDo>(),
+            rows,
+            self.style(todos.header_colour),
+        );
+    }
+
+    fn render_schedule(&self, frame: &mut Frame, area: Rect) {
+        let schedule = &self.config.schedule;
+        let rows = self
+            .events()
+            .iter()
+            .map(|(name, event)| {
+                let style = if event.start_time.1.is_none() {
+                    self.style(schedule.all_day_colour)
+                } else {
+                    Style::new()
+                };
+                let mut cells = vec![name.to_string()];
+                cells.extend(event.fields().into_iter().map(String::from));
+                Row::new(cells).style(style)
+            })
+            .collect();
+        let mut headers = vec!["Schedule".to_string()];
+        headers.extend(column_names::<CalEvent>());
+        self.render_table(
+            frame,
+            area,
+            headers,
+            rows,
+            self.style(schedule.header_colour),
+        );
+    }
+
+    fn render_logs(&self, frame: &mut Frame, area: Rect) {
+        let kinds = [
+            (&self.config.comments, &self.schedule.comments),
+            (&self.config.blockers, &self.schedule.blockers),
+            (&self.config.ideas, &self.schedule.ideas),
+        ];
+        let rows = kinds
+            .iter()
+            .flat_map(|(config, comments)| {
+                comments.iter().map(|comment: &Comment| {
+                    let mut cells = vec![config.heading.clone()];
+                    cells
+                        .extend(comment.fields().into_iter().map(String::from));
+                    Row::new(cells)
+                })
+            })
+            .collect();
+        let mut headers = vec!["Kind".to_string()];
+        headers.extend(column_names::<Comment>());
+        self.render_table(
+            frame,
+            area,
+            headers,
+            rows,
+            self.style(self.config.comments.header_colour),
+        );
+    }
+
+    fn render_calendar(&self, frame: &mut Frame, area: Rect) {
+        let first = self.day.with_day(1).expect("Every month has a first");
+        let next_month = first + Months::new(1);
+        let mut lines = vec![
+            Line::from(first.format("%B %Y").to_string()),
+            Line::from("Mo Tu We Th Fr Sa Su"),
+        ];
+
+        let mut week = vec![
+            Span::raw("   ");
+            first.weekday().num_days_from_monday() as usize
+        ];
+        for date in first.iter_days().take_while(|date| *date < next_month) {
+            let mut style = Style::new();
+            if self.notes.join(day_filename(&date)).exists() {
+                style =
+                    style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+            }
+            if date == self.day {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            week.push(Span::styled(format!("{:>2}", date.day()), style));
+            week.push(Span::raw(" "));
+            if date.weekday() == Weekday::Sun {
+                lines.push(Line::from(std::mem::take(&mut week)));
+            }
+        }
+        if !week.is_empty() {
+            lines.push(Line::from(week));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered()),
+            area,
+        );
+    }
+}
+
+/// Reads a day with an optional time, like `friday`, `tomorrow 3pm` or
+/// `2025-01-10 AT 15:30`.
+fn parse_when(
+    input: &str,
+    today: NaiveDate,
+) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    if let Some(date) = parse_date(input, today) {
+        return Some((date, None));
+    }
+    let (date, time) = input.trim().rsplit_once(char::is_whitespace)?;
+    let date = date.trim_end();
+    let date = date
+        .strip_suffix(" AT")
+        .or_else(|| date.strip_suffix(" at"))
+        .unwrap_or(date);
+    Some((parse_date(date, today)?, Some(parse_time(time).ok()?)))
+}
+
+fn column_names<T: Tabled>() -> Vec<String> {
+    T::headers().into_iter().map(String::from).collect()
+}
+
+/// Runs the full screen interface until the user quits.
+pub fn run(
+    config: &UpperConfig,
+    notes: PathBuf,
+    today: NaiveDate,
+    colour: bool,
+    filter: TagQuery,
+) -> Result<()> {
+    let mut app = App::new(config, notes, today, colour, filter);
+    let mut terminal = ratatui::try_init()?;
+
+    let result = (|| -> Result<()> {
+        while !app.quit {
+            terminal.draw(|frame| app.render(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.handle(key);
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    ratatui::try_restore()?;
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{
+        CommentConfig, ScheduleConfig, TodoConfig, UpperConfigBuilder,
+    };
+    use chrono_tz::Tz;
+    use crossterm::event::KeyModifiers;
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::fs;
+
+    fn config() -> UpperConfig {
+        UpperConfigBuilder::default()
+            .timezone(Some(Tz::UTC))
+            .todos(TodoConfig::default())
+            .schedule(ScheduleConfig::default())
+            .comments(CommentConfig::default())
+            .build()
+            .unwrap()
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for chr in keys.chars() {
+            let code = match chr {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                '<' => KeyCode::Left,
+                '>' => KeyCode::Right,
+                chr => KeyCode::Char(chr),
+            };
+            app.handle(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn check_navigation() {
+        let config = config();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        let mut app = App::new(
+            &config,
+            PathBuf::from("tests"),
+            today,
+            true,
+            TagQuery::default(),
+        );
+
+        assert!(screen(&app).contains("Fri 2025-01-10"));
+        press(&mut app, "\t\t");
+        assert_eq!(app.tab, Tab::Logs);
+        assert_eq!(app.rows(), 6);
+        assert!(screen(&app).contains("Cache the regexes"));
+
+        press(&mut app, "<");
+        assert_eq!(app.day, NaiveDate::from_ymd_opt(2025, 1, 9).unwrap());
+        press(&mut app, "2");
+        assert!(screen(&app).contains("Pairing session"));
+
+        press(&mut app, "4j");
+        assert_eq!(app.day, NaiveDate::from_ymd_opt(2025, 1, 16).unwrap());
+        assert!(screen(&app).contains("January 2025"));
+        press(&mut app, "t\nq");
+        assert_eq!((app.day, app.tab, app.quit), (today, Tab::Todos, true));
+    }
+
+    #[test]
+    fn check_filter() {
+        let config = config();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let filter = TagQuery {
+            tags: vec!["work".to_string()],
+            contexts: Vec::new(),
+        };
+        let mut app =
+            App::new(&config, PathBuf::from("tests"), today, false, filter);
+        assert_eq!(app.rows(), 2);
+        press(&mut app, "2");
+        assert!(screen(&app).contains("Standup"));
+        press(&mut app, "3");
+        assert_eq!(app.rows(), 0);
+    }
+
+    #[test]
+    fn check_actions() {
+        let config = config();
+        let notes = tempfile::tempdir().unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let path = notes.path().join(day_filename(&today));
+        fs::write(&path, "# Day\n").unwrap();
+
+        let mut app = App::new(
+            &config,
+            notes.path().to_path_buf(),
+            today,
+            false,
+            TagQuery::default(),
+        );
+        press(&mut app, "aWrite the tests\naShip it\n");
+        assert_eq!(app.schedule.tbd_todos.len(), 2);
+
+        press(&mut app, "x");
+        assert_eq!(app.message.as_deref(), Some("Marked the todo done"));
+        press(&mut app, "r3d\n");
+        assert_eq!(app.message.as_deref(), Some("Due 2025-01-09"));
+        press(&mut app, "rsoon\n");
+        assert_eq!(app.message.as_deref(), Some("Not a date: soon"));
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Day\n\n- [x] Write the tests\n- [ ] Ship it DEADLINE: 2025-01-09\n"
+        );
+        assert!(screen(&app).contains("Ship it"));
+    }
+
+    #[test]
+    fn check_reschedule_event() {
+        let config = config();
+        let notes = tempfile::tempdir().unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let path = notes.path().join(day_filename(&today));
+        fs::write(
+            &path,
+            "# Day\n\n> [!IMPORTANT]\n> Standup AT: 09:00 AM\n\n\
+             > [!IMPORTANT]\n> Review AT: 02:00 PM\n",
+        )
+        .unwrap();
+
+        let mut app = App::new(
+            &config,
+            notes.path().to_path_buf(),
+            today,
+            false,
+            TagQuery::default(),
+        );
+        press(&mut app, "r");
+        assert_eq!(app.message.as_deref(), Some("No todo or event selected"));
+
+        press(&mut app, "2j");
+        assert_eq!(
+            app.selected_event().map(|(name, _)| name.as_str()),
+            Some("Review")
+        );
+        press(&mut app, "rtomorrow 3pm\n");
+        assert_eq!(app.message.as_deref(), Some("Moved Review to 2025-01-07"));
+        assert_eq!(app.schedule.events.len(), 1);
+
+        let moved =
+            fs::read_to_string(notes.path().join("2025-01-07.md")).unwrap();
+        assert!(moved.contains("> Review AT: 03:00 PM"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("Review"));
+    }
+}